@@ -11,6 +11,27 @@ fn ns() -> Instant {
     Instant::now()
 }
 
+// -----------------------------------------------------------------------------
+// index hash experiments
+
+type ExperimentEncode =
+    fn(&rapid_qoi::Qoi, &[u8], &mut [u8]) -> Result<usize, rapid_qoi::EncodeError>;
+
+#[cfg(feature = "experimental")]
+const INDEX_EXPERIMENTS: &[(&str, ExperimentEncode)] = {
+    use rapid_qoi::experiment::*;
+    &[
+        ("spec/32", encode_experiment::<SpecHash, 32>),
+        ("fibonacci/64", encode_experiment::<FibonacciHash, 64>),
+        ("fibonacci/32", encode_experiment::<FibonacciHash, 32>),
+        ("xor/64", encode_experiment::<XorHash, 64>),
+        ("xor/32", encode_experiment::<XorHash, 32>),
+    ]
+};
+
+#[cfg(not(feature = "experimental"))]
+const INDEX_EXPERIMENTS: &[(&str, ExperimentEncode)] = &[];
+
 // -----------------------------------------------------------------------------
 // benchmark runner
 
@@ -27,6 +48,7 @@ struct BenchmarkResult {
     h: u32,
     qoi: BenchmarkLibResult,
     rapid_qoi: BenchmarkLibResult,
+    index_sizes: [u64; INDEX_EXPERIMENTS.len()],
 }
 
 #[inline(never)]
@@ -59,6 +81,7 @@ fn benchmark_image(path: &Path, runs: u32) -> BenchmarkResult {
             encode_time: Duration::ZERO,
             decode_time: Duration::ZERO,
         },
+        index_sizes: [0; INDEX_EXPERIMENTS.len()],
     };
 
     let image = match image::load(
@@ -122,6 +145,18 @@ fn benchmark_image(path: &Path, runs: u32) -> BenchmarkResult {
         *size = encoded.len() as u64;
     });
 
+    // Index hash experiments
+
+    let mut output = vec![0; encoded.len().max(pixels.len() * 5 / 4) + 64];
+    for (size, (_, encode)) in res.index_sizes.iter_mut().zip(INDEX_EXPERIMENTS) {
+        let q = rapid_qoi::Qoi {
+            width: w,
+            height: h,
+            colors: rapid_qoi::Colors::SrgbLinA,
+        };
+        *size = encode(&q, pixels, &mut output).unwrap() as u64;
+    }
+
     res
 }
 
@@ -160,6 +195,13 @@ fn benchmark_print_result(res: &BenchmarkResult) {
         },
         res.rapid_qoi.size / 1024,
     );
+    for (size, (name, _)) in res.index_sizes.iter().zip(INDEX_EXPERIMENTS) {
+        println!(
+            "index {:14}                                         {:8}",
+            name,
+            size / 1024
+        );
+    }
     println!();
 }
 
@@ -187,6 +229,7 @@ fn benchmark_directory(dirpath: &Path, runs: u32, grand_total: &mut BenchmarkRes
             encode_time: Duration::ZERO,
             decode_time: Duration::ZERO,
         },
+        index_sizes: [0; INDEX_EXPERIMENTS.len()],
     };
 
     for path in dir {
@@ -195,7 +238,7 @@ fn benchmark_directory(dirpath: &Path, runs: u32, grand_total: &mut BenchmarkRes
         if ft.is_file() {
             let filepath = Path::new(dirpath).join(path.file_name());
 
-            if filepath.extension().map_or(false, |e| e == "png") {
+            if filepath.extension().is_some_and(|e| e == "png") {
                 let res = benchmark_image(&filepath, runs);
 
                 dir_total.count += res.count;
@@ -209,6 +252,10 @@ fn benchmark_directory(dirpath: &Path, runs: u32, grand_total: &mut BenchmarkRes
                 dir_total.rapid_qoi.decode_time += res.rapid_qoi.decode_time;
                 dir_total.rapid_qoi.size += res.rapid_qoi.size;
 
+                for (total, size) in dir_total.index_sizes.iter_mut().zip(&res.index_sizes) {
+                    *total += size;
+                }
+
                 grand_total.qoi.encode_time += res.qoi.encode_time;
                 grand_total.qoi.decode_time += res.qoi.decode_time;
                 grand_total.qoi.size += res.qoi.size;
@@ -217,6 +264,10 @@ fn benchmark_directory(dirpath: &Path, runs: u32, grand_total: &mut BenchmarkRes
                 grand_total.rapid_qoi.decode_time += res.rapid_qoi.decode_time;
                 grand_total.rapid_qoi.size += res.rapid_qoi.size;
 
+                for (total, size) in grand_total.index_sizes.iter_mut().zip(&res.index_sizes) {
+                    *total += size;
+                }
+
                 grand_total.count += res.count;
                 grand_total.px += res.px;
 
@@ -240,6 +291,10 @@ fn benchmark_directory(dirpath: &Path, runs: u32, grand_total: &mut BenchmarkRes
         dir_total.rapid_qoi.decode_time /= dir_total.count;
         dir_total.rapid_qoi.size /= dir_total.count as u64;
 
+        for size in &mut dir_total.index_sizes {
+            *size /= dir_total.count as u64;
+        }
+
         println!("## Total for {}\n", dirpath.display());
         benchmark_print_result(&dir_total);
     }
@@ -277,6 +332,7 @@ fn main() -> Result<(), ()> {
             encode_time: Duration::ZERO,
             decode_time: Duration::ZERO,
         },
+        index_sizes: [0; INDEX_EXPERIMENTS.len()],
     };
 
     benchmark_directory(dirpath.as_ref(), runs, &mut grand_total);
@@ -294,6 +350,10 @@ fn main() -> Result<(), ()> {
         grand_total.rapid_qoi.decode_time /= grand_total.count;
         grand_total.rapid_qoi.size /= grand_total.count as u64;
 
+        for size in &mut grand_total.index_sizes {
+            *size /= grand_total.count as u64;
+        }
+
         println!("# Grand total for {}\n", dirpath);
         benchmark_print_result(&grand_total);
     }
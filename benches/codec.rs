@@ -0,0 +1,224 @@
+//! Benchmarks of `Qoi::encode_range` and `Qoi::decode_range` on synthetic images.\
+//! Each corpus is dominated by different class of ops,
+//! so regressions in handling of particular ops are visible separately.\
+//! Variants of `EncodeOptions` are measured with `Qoi::encode_with_options`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rapid_qoi::{Colors, EncodeOptions, Pixel, Qoi};
+
+const WIDTH: u32 = 512;
+const HEIGHT: u32 = 512;
+
+/// Function returning pixel at given coordinates.
+type Source = fn(u32, u32, &mut Lcg) -> [u8; 4];
+
+/// Deterministic pseudo-random generator, so corpora are the same for every run.
+struct Lcg(u32);
+
+impl Lcg {
+    fn next(&mut self) -> u32 {
+        self.0 = self.0.wrapping_mul(1103515245).wrapping_add(12345);
+        self.0 >> 8
+    }
+}
+
+/// Single color, encoded almost entirely with `QOI_OP_RUN`.
+fn flat(_x: u32, _y: u32, _rng: &mut Lcg) -> [u8; 4] {
+    [64, 128, 192, 255]
+}
+
+/// Smooth gradient with slight noise, encoded mostly with `QOI_OP_DIFF` and `QOI_OP_LUMA`.
+fn gradient(x: u32, y: u32, rng: &mut Lcg) -> [u8; 4] {
+    let n = (rng.next() % 3) as u8;
+    [
+        (x / 2) as u8 + n,
+        (y / 2) as u8 + n,
+        ((x + y) / 4) as u8,
+        255,
+    ]
+}
+
+/// Random colors, encoded mostly with `QOI_OP_RGB`.
+fn noise(_x: u32, _y: u32, rng: &mut Lcg) -> [u8; 4] {
+    let v = rng.next();
+    [v as u8, (v >> 8) as u8, (v >> 16) as u8, 255]
+}
+
+/// Flat panels with text-like strokes of few colors,
+/// encoded mostly with `QOI_OP_RUN` and `QOI_OP_INDEX`.
+fn screenshot(x: u32, y: u32, rng: &mut Lcg) -> [u8; 4] {
+    const PALETTE: [[u8; 4]; 6] = [
+        [255, 255, 255, 255],
+        [240, 240, 240, 255],
+        [30, 30, 30, 255],
+        [0, 120, 215, 255],
+        [200, 200, 200, 255],
+        [90, 90, 90, 255],
+    ];
+
+    let panel = ((x / 128) + (y / 96)) as usize % 2;
+    let line = y % 16 < 10 && x % 256 > 16 && x % 256 < 200;
+
+    if line && rng.next().is_multiple_of(4) {
+        PALETTE[2 + (rng.next() % 4) as usize]
+    } else {
+        PALETTE[panel]
+    }
+}
+
+fn generate(colors: Colors, f: Source) -> Vec<u8> {
+    let channels = colors.channels();
+    let mut rng = Lcg(7);
+    let mut pixels = Vec::with_capacity((WIDTH * HEIGHT) as usize * channels);
+
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            pixels.extend_from_slice(&f(x, y, &mut rng)[..channels]);
+        }
+    }
+    pixels
+}
+
+fn encode_range<const N: usize>(pixels: &[u8], output: &mut [u8]) -> usize
+where
+    [u8; N]: Pixel,
+{
+    Qoi::encode_range::<N>(
+        &mut [[0; 4]; 64],
+        &mut Pixel::new_opaque(),
+        &mut 0,
+        pixels,
+        output,
+    )
+    .unwrap()
+}
+
+fn decode_range<const N: usize>(bytes: &[u8], pixels: &mut [u8])
+where
+    [u8; N]: Pixel,
+{
+    Qoi::decode_range::<N>(
+        &mut [Pixel::new(); 64],
+        &mut Pixel::new_opaque(),
+        &mut 0,
+        bytes,
+        pixels,
+    )
+    .unwrap();
+}
+
+fn corpora() -> Vec<(&'static str, Colors, Vec<u8>)> {
+    let sources: [(&'static str, Source); 4] = [
+        ("flat", flat),
+        ("gradient", gradient),
+        ("noise", noise),
+        ("screenshot", screenshot),
+    ];
+
+    let mut corpora = Vec::new();
+    for &colors in &[Colors::Rgb, Colors::Rgba] {
+        for &(name, f) in &sources {
+            corpora.push((name, colors, generate(colors, f)));
+        }
+    }
+    corpora
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_range");
+    group.throughput(Throughput::Elements((WIDTH * HEIGHT) as u64));
+
+    for (name, colors, pixels) in corpora() {
+        let qoi = Qoi {
+            width: WIDTH,
+            height: HEIGHT,
+            colors,
+        };
+        let mut output = vec![0; qoi.encoded_size_limit()];
+
+        let id = BenchmarkId::new(name, colors.channels());
+        group.bench_with_input(id, &pixels, |b, pixels| match colors.has_alpha() {
+            true => b.iter(|| encode_range::<4>(pixels, &mut output)),
+            false => b.iter(|| encode_range::<3>(pixels, &mut output)),
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_range");
+    group.throughput(Throughput::Elements((WIDTH * HEIGHT) as u64));
+
+    for (name, colors, pixels) in corpora() {
+        let qoi = Qoi {
+            width: WIDTH,
+            height: HEIGHT,
+            colors,
+        };
+        let mut bytes = vec![0; qoi.encoded_size_limit()];
+        let size = match colors.has_alpha() {
+            true => encode_range::<4>(&pixels, &mut bytes),
+            false => encode_range::<3>(&pixels, &mut bytes),
+        };
+        bytes.truncate(size);
+
+        let mut output = vec![0; pixels.len()];
+
+        let id = BenchmarkId::new(name, colors.channels());
+        group.bench_with_input(id, &bytes, |b, bytes| match colors.has_alpha() {
+            true => b.iter(|| decode_range::<4>(bytes, &mut output)),
+            false => b.iter(|| decode_range::<3>(bytes, &mut output)),
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_encode_options(c: &mut Criterion) {
+    let variants = [
+        ("default", EncodeOptions::DEFAULT),
+        ("reference", EncodeOptions::REFERENCE),
+        (
+            "no-lookup",
+            EncodeOptions {
+                index_lookup: false,
+                ..EncodeOptions::DEFAULT
+            },
+        ),
+        (
+            "short-runs",
+            EncodeOptions {
+                max_run: 8,
+                ..EncodeOptions::DEFAULT
+            },
+        ),
+    ];
+
+    let mut group = c.benchmark_group("encode_options");
+    group.throughput(Throughput::Elements((WIDTH * HEIGHT) as u64));
+
+    for (name, colors, pixels) in corpora() {
+        let qoi = Qoi {
+            width: WIDTH,
+            height: HEIGHT,
+            colors,
+        };
+        let mut output = vec![0; qoi.encoded_size_limit()];
+
+        for (variant, options) in &variants {
+            let id = BenchmarkId::new(format!("{}/{}", name, variant), colors.channels());
+            group.bench_with_input(id, &pixels, |b, pixels| {
+                b.iter(|| {
+                    qoi.encode_with_options(pixels, &mut output, options)
+                        .unwrap()
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode, bench_decode, bench_encode_options);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+/*
+ * C API of rapid-qoi.
+ * Declarations match reference qoi.h, link with the `qoi` library built from this crate.
+ * Memory returned by the functions must be released with free().
+ */
+
+#ifndef RAPID_QOI_H
+#define RAPID_QOI_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define QOI_SRGB   0
+#define QOI_LINEAR 1
+
+typedef struct {
+	unsigned int width;
+	unsigned int height;
+	unsigned char channels;
+	unsigned char colorspace;
+} qoi_desc;
+
+int qoi_write(const char *filename, const void *data, const qoi_desc *desc);
+
+void *qoi_read(const char *filename, qoi_desc *desc, int channels);
+
+void *qoi_encode(const void *data, const qoi_desc *desc, int *out_len);
+
+void *qoi_decode(const void *data, int size, qoi_desc *desc, int channels);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RAPID_QOI_H */
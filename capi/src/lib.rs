@@ -0,0 +1,269 @@
+//! C API of rapid-qoi.
+//!
+//! Exports `qoi_encode`, `qoi_decode`, `qoi_read` and `qoi_write`
+//! with the same signatures and semantics as reference `qoi.h`,
+//! so C and C++ projects can link this library in place of the reference implementation.\
+//! Declarations are provided in `include/qoi.h`.
+//!
+//! Memory returned by this library is allocated with `malloc` and must be released with `free`.
+
+use std::{
+    ffi::{c_char, c_int, c_uint, c_void, CStr},
+    ptr, slice,
+};
+
+use rapid_qoi::{Colors, Qoi};
+
+/// Limit of pixels in image, same as in reference implementation.
+const QOI_PIXELS_MAX: u32 = 400_000_000;
+
+extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+}
+
+/// Image description, same as `qoi_desc` of reference implementation.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct qoi_desc {
+    pub width: c_uint,
+    pub height: c_uint,
+    pub channels: u8,
+    pub colorspace: u8,
+}
+
+impl qoi_desc {
+    /// Returns QOI header for the description.\
+    /// Returns `None` if description is invalid.
+    fn qoi(&self) -> Option<Qoi> {
+        let colors = match (self.channels, self.colorspace) {
+            (3, 0) => Colors::Srgb,
+            (4, 0) => Colors::SrgbLinA,
+            (3, 1) => Colors::Rgb,
+            (4, 1) => Colors::Rgba,
+            _ => return None,
+        };
+
+        if self.width == 0 || self.height == 0 || self.height >= QOI_PIXELS_MAX / self.width {
+            return None;
+        }
+
+        Some(Qoi {
+            width: self.width,
+            height: self.height,
+            colors,
+        })
+    }
+
+    fn from_qoi(qoi: &Qoi) -> Self {
+        qoi_desc {
+            width: qoi.width,
+            height: qoi.height,
+            channels: qoi.colors.channels() as u8,
+            colorspace: match qoi.colors {
+                Colors::Srgb | Colors::SrgbLinA => 0,
+                Colors::Rgb | Colors::Rgba => 1,
+            },
+        }
+    }
+}
+
+/// Copies `bytes` into memory allocated with `malloc`.\
+/// Returns null pointer if allocation fails.
+fn to_malloc(bytes: &[u8]) -> *mut c_void {
+    // SAFETY: `malloc` may be called with any size.
+    let ptr = unsafe { malloc(bytes.len()) };
+    if !ptr.is_null() {
+        // SAFETY: `ptr` points to allocated block of `bytes.len()` bytes.
+        unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.cast(), bytes.len()) };
+    }
+    ptr
+}
+
+/// Encodes raw RGB or RGBA pixels described by `desc` into a QOI image.
+/// Returns `malloc`ed buffer with encoded image and writes its size into `out_len`.\
+/// Returns null pointer on invalid arguments or failure.
+///
+/// # Safety
+///
+/// `data` must point to `width * height * channels` readable bytes,
+/// `desc` must point to valid `qoi_desc` and `out_len` must point to writable `int`.
+#[no_mangle]
+pub unsafe extern "C" fn qoi_encode(
+    data: *const c_void,
+    desc: *const qoi_desc,
+    out_len: *mut c_int,
+) -> *mut c_void {
+    if data.is_null() || desc.is_null() || out_len.is_null() {
+        return ptr::null_mut();
+    }
+
+    let qoi = match (*desc).qoi() {
+        None => return ptr::null_mut(),
+        Some(qoi) => qoi,
+    };
+
+    let len = qoi.decoded_size();
+    let pixels = slice::from_raw_parts(data.cast::<u8>(), len);
+
+    let bytes = match qoi.encode_alloc(pixels) {
+        Err(_) => return ptr::null_mut(),
+        Ok(bytes) => bytes,
+    };
+
+    let size = match c_int::try_from(bytes.len()) {
+        Err(_) => return ptr::null_mut(),
+        Ok(size) => size,
+    };
+
+    let ptr = to_malloc(&bytes);
+    if !ptr.is_null() {
+        *out_len = size;
+    }
+    ptr
+}
+
+/// Decodes QOI image from `size` bytes at `data`.
+/// Returns `malloc`ed buffer with pixels and fills `desc` with image description.\
+/// `channels` selects number of channels in output, `0` for the channels of the image.\
+/// Returns null pointer on invalid arguments or failure,
+/// including images with `QOI_PIXELS_MAX` or more pixels, same as reference implementation.
+///
+/// # Safety
+///
+/// `data` must point to `size` readable bytes and `desc` must point to writable `qoi_desc`.
+#[no_mangle]
+pub unsafe extern "C" fn qoi_decode(
+    data: *const c_void,
+    size: c_int,
+    desc: *mut qoi_desc,
+    channels: c_int,
+) -> *mut c_void {
+    if data.is_null() || desc.is_null() || size < 0 {
+        return ptr::null_mut();
+    }
+
+    let bytes = slice::from_raw_parts(data.cast::<u8>(), size as usize);
+
+    match decode(bytes, channels) {
+        None => ptr::null_mut(),
+        Some((qoi, pixels)) => {
+            *desc = qoi_desc::from_qoi(&qoi);
+            to_malloc(&pixels)
+        }
+    }
+}
+
+/// Reads and decodes QOI image from file.
+/// Returns `malloc`ed buffer with pixels and fills `desc` with image description.\
+/// `channels` selects number of channels in output, `0` for the channels of the image.\
+/// Returns null pointer on invalid arguments or failure,
+/// including images with `QOI_PIXELS_MAX` or more pixels, same as reference implementation.
+///
+/// # Safety
+///
+/// `filename` must point to nul-terminated string and `desc` must point to writable `qoi_desc`.
+#[no_mangle]
+pub unsafe extern "C" fn qoi_read(
+    filename: *const c_char,
+    desc: *mut qoi_desc,
+    channels: c_int,
+) -> *mut c_void {
+    if filename.is_null() || desc.is_null() {
+        return ptr::null_mut();
+    }
+
+    let bytes = match path(filename).and_then(|path| std::fs::read(path).ok()) {
+        None => return ptr::null_mut(),
+        Some(bytes) => bytes,
+    };
+
+    match decode(&bytes, channels) {
+        None => ptr::null_mut(),
+        Some((qoi, pixels)) => {
+            *desc = qoi_desc::from_qoi(&qoi);
+            to_malloc(&pixels)
+        }
+    }
+}
+
+/// Encodes raw RGB or RGBA pixels described by `desc` and writes QOI image into file.\
+/// Returns number of bytes written or `0` on invalid arguments or failure.
+///
+/// # Safety
+///
+/// `filename` must point to nul-terminated string,
+/// `data` must point to `width * height * channels` readable bytes
+/// and `desc` must point to valid `qoi_desc`.
+#[no_mangle]
+pub unsafe extern "C" fn qoi_write(
+    filename: *const c_char,
+    data: *const c_void,
+    desc: *const qoi_desc,
+) -> c_int {
+    if filename.is_null() || data.is_null() || desc.is_null() {
+        return 0;
+    }
+
+    let qoi = match (*desc).qoi() {
+        None => return 0,
+        Some(qoi) => qoi,
+    };
+
+    let pixels = slice::from_raw_parts(data.cast::<u8>(), qoi.decoded_size());
+
+    let bytes = match qoi.encode_alloc(pixels) {
+        Err(_) => return 0,
+        Ok(bytes) => bytes,
+    };
+
+    let size = match c_int::try_from(bytes.len()) {
+        Err(_) => return 0,
+        Ok(size) => size,
+    };
+
+    match path(filename).map(|path| std::fs::write(path, &bytes)) {
+        Some(Ok(())) => size,
+        _ => 0,
+    }
+}
+
+/// Decodes image into pixels with requested number of channels.\
+/// Images with [`QOI_PIXELS_MAX`] or more pixels are rejected before allocating pixels.
+fn decode(bytes: &[u8], channels: c_int) -> Option<(Qoi, Vec<u8>)> {
+    let qoi = Qoi::decode_header(bytes).ok()?;
+    qoi_desc::from_qoi(&qoi).qoi()?;
+
+    let (channels, colors) = match channels {
+        0 => return Qoi::decode_alloc(bytes).ok(),
+        3 => (rapid_qoi::Channels::Rgb, Colors::Rgb),
+        4 => (rapid_qoi::Channels::Rgba, Colors::Rgba),
+        _ => return None,
+    };
+
+    // Size of output with requested channels, regardless of channels of the image.
+    let size = Qoi { colors, ..qoi }.checked_decoded_size()?;
+
+    let mut pixels = vec![0; size];
+    let qoi = Qoi::decode_with_channels(bytes, &mut pixels, channels).ok()?;
+    Some((qoi, pixels))
+}
+
+/// Converts nul-terminated file name into path.
+///
+/// # Safety
+///
+/// `filename` must point to nul-terminated string.
+unsafe fn path(filename: *const c_char) -> Option<std::path::PathBuf> {
+    let filename = CStr::from_ptr(filename);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(std::ffi::OsStr::from_bytes(filename.to_bytes()).into())
+    }
+
+    #[cfg(not(unix))]
+    {
+        filename.to_str().ok().map(Into::into)
+    }
+}
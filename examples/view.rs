@@ -0,0 +1,26 @@
+//! Shows QOI image in a window.
+//!
+//! Run `cargo run --example view --features viewer -- <path>`
+
+use std::path::PathBuf;
+
+fn main() -> Result<(), ()> {
+    let mut args = std::env::args();
+
+    if args.len() != 2 {
+        eprintln!("Usage: view <path>");
+        return Err(());
+    }
+
+    args.next();
+
+    let path = PathBuf::from(args.next().unwrap());
+
+    let bytes = std::fs::read(&path)
+        .map_err(|err| eprintln!("Failed to read QOI file '{}'. {:#}", path.display(), err))?;
+
+    rapid_qoi::viewer::show(&path.display().to_string(), &bytes)
+        .map_err(|err| eprintln!("Failed to show QOI image '{}'. {:#}", path.display(), err))?;
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Python bindings of rapid-qoi.
+//!
+//! Build with `maturin build` in this directory to get `rapid_qoi` Python module
+//! exporting `encode` and `decode` functions operating on numpy arrays.
+
+use ::rapid_qoi::{Colors, Qoi};
+use numpy::{PyArray1, PyArray3, PyArrayMethods, PyReadonlyArrayDyn, PyUntypedArrayMethods};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+
+/// Shape of image array, `(height, width, channels)`.
+type Shape = (usize, usize, usize);
+
+/// Encodes `uint8` array of shape `(height, width, channels)` into QOI image.
+/// `channels` must be 3 or 4.
+/// Color channels are encoded as sRGB, unless `linear` is set.
+#[pyfunction]
+#[pyo3(signature = (array, linear = false))]
+fn encode<'py>(
+    py: Python<'py>,
+    array: PyReadonlyArrayDyn<'py, u8>,
+    linear: bool,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let (height, width, channels) = match *array.shape() {
+        [height, width, channels] => (height, width, channels),
+        _ => return Err(PyValueError::new_err("array must have 3 dimensions")),
+    };
+
+    let colors = match (channels, linear) {
+        (3, false) => Colors::Srgb,
+        (4, false) => Colors::SrgbLinA,
+        (3, true) => Colors::Rgb,
+        (4, true) => Colors::Rgba,
+        _ => return Err(PyValueError::new_err("array must have 3 or 4 channels")),
+    };
+
+    let qoi = match (u32::try_from(width), u32::try_from(height)) {
+        (Ok(width), Ok(height)) => Qoi {
+            width,
+            height,
+            colors,
+        },
+        _ => return Err(PyValueError::new_err("image is too large")),
+    };
+
+    let pixels = array.as_slice()?;
+    let bytes = py
+        .allow_threads(|| qoi.encode_alloc(pixels))
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+    Ok(PyBytes::new(py, &bytes))
+}
+
+/// Decodes QOI image into `uint8` array of shape `(height, width, channels)`.
+/// Returns `(shape, array)` tuple.
+#[pyfunction]
+fn decode<'py>(py: Python<'py>, bytes: &[u8]) -> PyResult<(Shape, Bound<'py, PyArray3<u8>>)> {
+    let (qoi, pixels) = py
+        .allow_threads(|| Qoi::decode_alloc(bytes))
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+    let shape = (
+        qoi.height as usize,
+        qoi.width as usize,
+        qoi.colors.channels(),
+    );
+
+    let array = PyArray1::from_vec(py, pixels).reshape([shape.0, shape.1, shape.2])?;
+    Ok((shape, array))
+}
+
+#[pymodule]
+fn rapid_qoi(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(encode, m)?)?;
+    m.add_function(wrap_pyfunction!(decode, m)?)?;
+    Ok(())
+}
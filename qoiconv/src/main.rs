@@ -1,10 +1,13 @@
 use std::path::PathBuf;
 
-use image::{DynamicImage, ImageFormat};
+use image::ImageFormat;
+use rapid_qoi::DynamicImageQoiExt;
 
 enum Format {
     Qoi,
     Raw,
+    Gpl,
+    Act,
     Image(image::ImageFormat),
 }
 
@@ -25,6 +28,7 @@ fn main() -> Result<(), ()> {
         Some(ext) => match ext {
             _ if ext == "qoi" => Format::Qoi,
             _ if ext == "raw" => panic!("RAW is unsupported as input format"),
+            _ if ext == "gpl" || ext == "act" => panic!("Palette is unsupported as input format"),
             _ => Format::Image(ImageFormat::from_extension(ext).ok_or_else(|| {
                 eprintln!(
                     "Failed to pick output format based on extension '{:?}'",
@@ -55,6 +59,8 @@ fn main() -> Result<(), ()> {
         Some(ext) => match ext {
             _ if ext == "qoi" => Format::Qoi,
             _ if ext == "raw" => Format::Raw,
+            _ if ext == "gpl" => Format::Gpl,
+            _ if ext == "act" => Format::Act,
             _ => Format::Image(ImageFormat::from_extension(ext).ok_or_else(|| {
                 eprintln!(
                     "Failed to pick output format based on extension '{:?}'",
@@ -72,101 +78,86 @@ fn main() -> Result<(), ()> {
         .map_err(|err| eprintln!("Failed to read QOI file '{}'. {:#}", input.display(), err))?;
 
     let dynamic_image = match input_format {
+        Format::Qoi => rapid_qoi::Qoi::decode_image(&bytes).map_err(|err| {
+            eprintln!(
+                "Failed to decode QOI image '{}'. {:#?}",
+                input.display(),
+                err
+            )
+        })?,
+        Format::Raw | Format::Gpl | Format::Act => unreachable!(),
+        Format::Image(format) => {
+            image::load_from_memory_with_format(&bytes, format).map_err(|err| {
+                eprintln!(
+                    "Failed to open input image '{}'. {:#}",
+                    input.display(),
+                    err
+                )
+            })?
+        }
+    };
+
+    match output_format {
         Format::Qoi => {
-            let (qoi, pixels) = rapid_qoi::Qoi::decode_alloc(&bytes).map_err(|err| {
+            let bytes = dynamic_image.to_qoi().map_err(|err| {
                 eprintln!(
-                    "Failed to decode QOI image '{}'. {:#?}",
+                    "Failed to encode QOI image from '{}'. {:#?}",
                     input.display(),
                     err
                 )
             })?;
 
-            match qoi.colors.has_alpha() {
-                true => image::DynamicImage::ImageRgba8(
-                    image::RgbaImage::from_raw(qoi.width, qoi.height, pixels).unwrap(),
-                ),
-                false => image::DynamicImage::ImageRgb8(
-                    image::RgbImage::from_raw(qoi.width, qoi.height, pixels).unwrap(),
-                ),
-            }
+            std::fs::write(&output, &bytes).map_err(|err| {
+                eprintln!(
+                    "Failed to write QOI image into output file {}. {:#}",
+                    output.display(),
+                    err
+                )
+            })?;
         }
-        Format::Raw => unreachable!(),
-        Format::Image(format) => {
-            image::load_from_memory_with_format(&bytes, format).map_err(|err| {
+
+        Format::Raw => {
+            std::fs::write(&output, dynamic_image.as_bytes()).map_err(|err| {
                 eprintln!(
-                    "Failed to open input image '{}'. {:#}",
-                    input.display(),
+                    "Failed to write RAW image into output file {}. {:#}",
+                    output.display(),
                     err
                 )
-            })?
+            })?;
         }
-    };
 
-    match output_format {
-        Format::Qoi => match dynamic_image {
-            DynamicImage::ImageLuma16(_)
-            | DynamicImage::ImageLuma8(_)
-            | DynamicImage::ImageLumaA16(_)
-            | DynamicImage::ImageLumaA8(_)
-            | DynamicImage::ImageRgba16(_)
-            | DynamicImage::ImageRgba8(_) => {
-                let rgba = dynamic_image.to_rgba8();
-                let pixels = rgba.as_raw();
-
-                let qoi = rapid_qoi::Qoi {
-                    width: rgba.width(),
-                    height: rgba.height(),
-                    colors: rapid_qoi::Colors::SrgbLinA,
-                };
-
-                let bytes = qoi.encode_alloc(pixels).map_err(|err| {
+        Format::Gpl | Format::Act => {
+            let qoi = match input_format {
+                Format::Qoi => bytes,
+                _ => dynamic_image.to_qoi().map_err(|err| {
                     eprintln!(
-                        "Failed to encode QOI image '{}. {:#?}",
+                        "Failed to encode QOI image from '{}'. {:#?}",
                         input.display(),
                         err
                     )
-                })?;
+                })?,
+            };
 
-                std::fs::write(&output, &bytes).map_err(|err| {
-                    eprintln!(
-                        "Failed to write QOI image into output file {}. {:#}",
-                        output.display(),
-                        err
-                    )
-                })?;
-            }
-            _ => {
-                let rgb = dynamic_image.to_rgb8();
-                let pixels = rgb.as_raw();
-
-                let qoi = rapid_qoi::Qoi {
-                    width: rgb.width(),
-                    height: rgb.height(),
-                    colors: rapid_qoi::Colors::Srgb,
-                };
-
-                let bytes = qoi.encode_alloc(pixels).map_err(|err| {
+            let palette = rapid_qoi::palette::extract(&qoi, rapid_qoi::palette::ACT_MAX_COLORS)
+                .map_err(|err| {
                     eprintln!(
-                        "Failed to encode QOI image '{}. {:#?}",
+                        "Failed to extract palette from '{}'. {:#?}",
                         input.display(),
                         err
                     )
                 })?;
 
-                std::fs::write(&output, &bytes).map_err(|err| {
-                    eprintln!(
-                        "Failed to write QOI image into output file {}. {:#}",
-                        output.display(),
-                        err
-                    )
-                })?;
-            }
-        },
+            let bytes = match output_format {
+                Format::Gpl => {
+                    let name = input.file_stem().unwrap_or_default().to_string_lossy();
+                    rapid_qoi::palette::to_gpl(&name, &palette).into_bytes()
+                }
+                _ => rapid_qoi::palette::to_act(&palette),
+            };
 
-        Format::Raw => {
-            std::fs::write(&output, &dynamic_image.as_bytes()).map_err(|err| {
+            std::fs::write(&output, &bytes).map_err(|err| {
                 eprintln!(
-                    "Failed to write RAW image into output file {}. {:#}",
+                    "Failed to write palette into output file {}. {:#}",
                     output.display(),
                     err
                 )
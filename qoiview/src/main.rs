@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use minifb::{Key, KeyRepeat, ScaleMode, Window, WindowOptions};
+use rapid_qoi::{PackedLayout, Qoi};
+
+const MAX_ZOOM: usize = 16;
+const CHECKER_SIZE: usize = 8;
+const CHECKER_LIGHT: u8 = 0xcc;
+const CHECKER_DARK: u8 = 0x88;
+
+struct Image {
+    path: PathBuf,
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+fn load(path: PathBuf) -> Result<Image, ()> {
+    let bytes = std::fs::read(&path)
+        .map_err(|err| eprintln!("Failed to read QOI file '{}'. {:#}", path.display(), err))?;
+
+    let qoi = Qoi::decode_header(&bytes).map_err(|err| {
+        eprintln!(
+            "Failed to decode QOI header '{}'. {:#?}",
+            path.display(),
+            err
+        )
+    })?;
+
+    let mut pixels = vec![0; qoi.width as usize * qoi.height as usize];
+    Qoi::decode_u32(&bytes, PackedLayout::Argb, &mut pixels).map_err(|err| {
+        eprintln!(
+            "Failed to decode QOI image '{}'. {:#?}",
+            path.display(),
+            err
+        )
+    })?;
+
+    Ok(Image {
+        path,
+        width: qoi.width as usize,
+        height: qoi.height as usize,
+        pixels,
+    })
+}
+
+fn blend(c: u8, bg: u8, a: u8) -> u8 {
+    ((c as u32 * a as u32 + bg as u32 * (255 - a as u32) + 127) / 255) as u8
+}
+
+/// Renders image scaled by `zoom` into `framebuffer`.
+/// When `checkerboard` is set transparent pixels are composed over checkerboard pattern,
+/// otherwise alpha channel is ignored.
+fn render(image: &Image, zoom: usize, checkerboard: bool, framebuffer: &mut Vec<u32>) {
+    let width = image.width * zoom;
+    let height = image.height * zoom;
+
+    framebuffer.clear();
+    framebuffer.reserve(width * height);
+
+    for y in 0..height {
+        let row = &image.pixels[y / zoom * image.width..][..image.width];
+        for x in 0..width {
+            let [a, r, g, b] = row[x / zoom].to_be_bytes();
+
+            let px = if checkerboard {
+                let bg = match (x / CHECKER_SIZE + y / CHECKER_SIZE) % 2 {
+                    0 => CHECKER_LIGHT,
+                    _ => CHECKER_DARK,
+                };
+                [0, blend(r, bg, a), blend(g, bg, a), blend(b, bg, a)]
+            } else {
+                [0, r, g, b]
+            };
+
+            framebuffer.push(u32::from_be_bytes(px));
+        }
+    }
+}
+
+fn main() -> Result<(), ()> {
+    let mut args = std::env::args();
+
+    if args.len() < 2 {
+        eprintln!("Usage: qoiview <path>...");
+        eprintln!("Example: qoiview images/foo.qoi images/bar.qoi");
+        eprintln!("Files may also be dropped onto the executable.");
+        eprintln!();
+        eprintln!("Controls:");
+        eprintln!("  Left/Right  previous/next image");
+        eprintln!("  +/-         zoom in/out");
+        eprintln!("  A           toggle alpha checkerboard");
+        eprintln!("  Escape      quit");
+        return Err(());
+    }
+
+    args.next();
+
+    let images = args
+        .map(PathBuf::from)
+        .filter_map(|path| load(path).ok())
+        .collect::<Vec<_>>();
+
+    if images.is_empty() {
+        eprintln!("No images to show");
+        return Err(());
+    }
+
+    let mut current = 0;
+    let mut zoom = 1;
+    let mut checkerboard = true;
+    let mut dirty = true;
+    let mut framebuffer = Vec::new();
+
+    let mut window = Window::new(
+        "qoiview",
+        images[0].width.max(256),
+        images[0].height.max(256),
+        WindowOptions {
+            resize: true,
+            scale_mode: ScaleMode::Center,
+            ..WindowOptions::default()
+        },
+    )
+    .map_err(|err| eprintln!("Failed to open window. {:#}", err))?;
+
+    window.set_target_fps(60);
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        for key in window.get_keys_pressed(KeyRepeat::Yes) {
+            match key {
+                Key::Right | Key::PageDown | Key::Space => {
+                    current = (current + 1) % images.len();
+                }
+                Key::Left | Key::PageUp | Key::Backspace => {
+                    current = (current + images.len() - 1) % images.len();
+                }
+                Key::Equal | Key::NumPadPlus => zoom = (zoom + 1).min(MAX_ZOOM),
+                Key::Minus | Key::NumPadMinus => zoom = (zoom - 1).max(1),
+                Key::A => checkerboard = !checkerboard,
+                _ => continue,
+            }
+            dirty = true;
+        }
+
+        let image = &images[current];
+
+        if dirty {
+            dirty = false;
+            render(image, zoom, checkerboard, &mut framebuffer);
+            window.set_title(&format!(
+                "qoiview - {} ({}/{}) {}x{} x{}",
+                image.path.display(),
+                current + 1,
+                images.len(),
+                image.width,
+                image.height,
+                zoom,
+            ));
+        }
+
+        window
+            .update_with_buffer(&framebuffer, image.width * zoom, image.height * zoom)
+            .map_err(|err| eprintln!("Failed to update window. {:#}", err))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,30 @@
+//! Alpha channel utilities.
+
+/// Alpha bytes of two RGBA pixels read as little-endian `u64`.
+const ALPHA_MASK: u64 = 0xFF00_0000_FF00_0000;
+
+/// Number of bytes checked between early exits.
+const BLOCK: usize = 64;
+
+/// Returns `true` if every pixel in raw RGBA `pixels` has alpha equal to `255`.\
+/// Pixels are checked in wide blocks that compiler turns into SIMD instructions.
+///
+/// Trailing bytes of `pixels` that do not form a whole pixel are ignored.
+pub fn all_opaque(pixels: &[u8]) -> bool {
+    let mut blocks = pixels.chunks_exact(BLOCK);
+
+    for block in &mut blocks {
+        let acc = block
+            .as_chunks::<8>()
+            .0
+            .iter()
+            .map(|&word| u64::from_le_bytes(word))
+            .fold(u64::MAX, |acc, word| acc & word);
+
+        if acc & ALPHA_MASK != ALPHA_MASK {
+            return false;
+        }
+    }
+
+    blocks.remainder().chunks_exact(4).all(|px| px[3] == 255)
+}
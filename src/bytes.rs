@@ -0,0 +1,140 @@
+use super::*;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Source of encoded bytes for decoding.
+pub(crate) trait ByteSource {
+    /// Returns bytes available for decoding.
+    fn bytes(&self) -> &[u8];
+
+    /// Marks first `n` available bytes as decoded.\
+    /// Consuming more bytes than available consumes all of them.
+    fn consume(&mut self, n: usize);
+}
+
+impl ByteSource for &[u8] {
+    #[inline]
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+
+    #[inline]
+    fn consume(&mut self, n: usize) {
+        *self = self.get(n..).unwrap_or_default();
+    }
+}
+
+/// Destination for encoded bytes.
+pub(crate) trait ByteSink {
+    /// Returns writable buffer for encoding.\
+    /// Buffer is at least `hint` bytes long unless sink cannot grow.
+    fn buffer(&mut self, hint: usize) -> &mut [u8];
+
+    /// Marks first `n` bytes of the buffer as written.
+    fn advance(&mut self, n: usize);
+
+    /// Writes all `bytes` into the sink.
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), EncodeError> {
+        match self.buffer(bytes.len()).get_mut(..bytes.len()) {
+            None => {
+                cold();
+                return Err(EncodeError::OutputIsTooSmall);
+            }
+            Some(buffer) => buffer.copy_from_slice(bytes),
+        }
+        self.advance(bytes.len());
+        Ok(())
+    }
+}
+
+/// Sink that writes into fixed-size slice.
+#[derive(Debug)]
+pub(crate) struct SliceSink<'a> {
+    output: &'a mut [u8],
+    written: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    #[inline]
+    pub fn new(output: &'a mut [u8]) -> Self {
+        SliceSink { output, written: 0 }
+    }
+
+    /// Returns number of bytes written.
+    #[inline]
+    pub fn written(&self) -> usize {
+        self.written
+    }
+}
+
+impl ByteSink for SliceSink<'_> {
+    #[inline]
+    fn buffer(&mut self, _hint: usize) -> &mut [u8] {
+        self.output.get_mut(self.written..).unwrap_or_default()
+    }
+
+    #[inline]
+    fn advance(&mut self, n: usize) {
+        self.written += n;
+    }
+}
+
+/// Sink that writes into growing `Vec`.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Default)]
+pub(crate) struct VecSink {
+    output: Vec<u8>,
+    written: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl VecSink {
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        VecSink {
+            output: Vec::with_capacity(capacity),
+            written: 0,
+        }
+    }
+
+    /// Returns sink that appends after existing content of `output`.\
+    /// Capacity of `output` is reused.
+    #[inline]
+    pub fn from_vec(output: Vec<u8>) -> Self {
+        let written = output.len();
+        VecSink { output, written }
+    }
+
+    /// Returns number of bytes in the sink, including content it was created with.
+    #[inline]
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Returns written bytes.
+    #[inline]
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.output.truncate(self.written);
+        self.output
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ByteSink for VecSink {
+    #[inline]
+    fn buffer(&mut self, hint: usize) -> &mut [u8] {
+        // Only `hint` bytes ahead of written data are initialized,
+        // reserved capacity for the rest of the image stays untouched.
+        if self.output.len() < self.written + hint {
+            self.output.resize(self.written + hint, 0);
+        }
+        &mut self.output[self.written..]
+    }
+
+    #[inline]
+    fn advance(&mut self, n: usize) {
+        self.written += n;
+    }
+}
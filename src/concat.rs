@@ -0,0 +1,53 @@
+use super::*;
+
+/// Iterator over QOI images stored back-to-back in one buffer.\
+/// Yields header and bytes of each image including its header and end marker.
+/// Yields `Err` once if buffer contains malformed or truncated image.
+///
+/// Returned by [`Qoi::split_concatenated`].
+#[derive(Clone, Debug)]
+pub struct Concatenated<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Concatenated<'a> {
+    /// Returns bytes that are not split yet.
+    #[inline]
+    pub fn remainder(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl<'a> Iterator for Concatenated<'a> {
+    type Item = Result<(Qoi, &'a [u8]), DecodeError>;
+
+    fn next(&mut self) -> Option<Result<(Qoi, &'a [u8]), DecodeError>> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        let result =
+            Qoi::decode_header(self.bytes).and_then(|qoi| Ok((qoi, Qoi::encoded_len(self.bytes)?)));
+
+        match result {
+            Ok((qoi, len)) => {
+                let (image, rest) = self.bytes.split_at(len);
+                self.bytes = rest;
+                Some(Ok((qoi, image)))
+            }
+            Err(err) => {
+                self.bytes = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl Qoi {
+    /// Splits buffer with several QOI images appended one after another.\
+    /// Each image can be decoded with [`Qoi::decode`] or [`Qoi::decode_alloc`].
+    #[inline]
+    pub fn split_concatenated(bytes: &[u8]) -> Concatenated<'_> {
+        Concatenated { bytes }
+    }
+}
@@ -0,0 +1,95 @@
+use super::*;
+
+use alloc::{string::String, vec::Vec};
+
+/// Prefix of data URI with base64 encoded QOI image.
+const DATA_URI_PREFIX: &str = "data:image/qoi;base64,";
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8], output: &mut String) {
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let v = u32::from_be_bytes([0, b[0], b[1], b[2]]);
+
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => output.push(BASE64_ALPHABET[(v >> (18 - 6 * i)) as usize & 63] as char),
+                false => output.push('='),
+            }
+        }
+    }
+}
+
+fn base64_value(c: u8) -> Option<u32> {
+    match c {
+        b'A'..=b'Z' => Some((c - b'A') as u32),
+        b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+        b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+/// Decodes base64 string with optional padding.\
+/// Returns `None` if string contains invalid characters.
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim_end_matches('=').as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3 + 2);
+
+    for chunk in encoded.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+
+        let mut v = 0;
+        for (i, &c) in chunk.iter().enumerate() {
+            v |= base64_value(c)? << (18 - 6 * i);
+        }
+
+        bytes.extend_from_slice(&v.to_be_bytes()[1..chunk.len()]);
+    }
+
+    Some(bytes)
+}
+
+impl Qoi {
+    /// Encode raw RGB or RGBA pixels into a QOI image
+    /// and returns it as `data:image/qoi;base64,...` URI.
+    ///
+    /// On success this function returns `Ok(uri)` with `uri` containing encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_data_uri(&self, pixels: &[u8]) -> Result<String, EncodeError> {
+        let bytes = self.encode_alloc(pixels)?;
+
+        let mut uri = String::with_capacity(DATA_URI_PREFIX.len() + bytes.len().div_ceil(3) * 4);
+        uri.push_str(DATA_URI_PREFIX);
+        base64_encode(&bytes, &mut uri);
+        Ok(uri)
+    }
+
+    /// Decode a QOI image from `data:image/qoi;base64,...` URI.\
+    /// Decoded raw RGB or RGBA pixels are written into allocated `Vec`.
+    ///
+    /// On success this function returns `Ok((qoi, vec))` with `qoi` describing image dimensions and color space and `vec` containing raw pixels data.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_data_uri(uri: &str) -> Result<(Self, Vec<u8>), DecodeError> {
+        let encoded = match uri.strip_prefix(DATA_URI_PREFIX) {
+            None => return Err(DecodeError::InvalidDataUri),
+            Some(encoded) => encoded,
+        };
+
+        let bytes = match base64_decode(encoded) {
+            None => return Err(DecodeError::InvalidDataUri),
+            Some(bytes) => bytes,
+        };
+
+        Self::decode_alloc(&bytes)
+    }
+}
@@ -1,18 +1,22 @@
-use core::convert::TryInto;
+use core::convert::TryFrom;
 
 use super::*;
 
+use crate::bytes::ByteSource;
+use crate::ops::{op_pixels, op_size};
+
 #[cfg(feature = "alloc")]
-use alloc::{vec, vec::Vec};
+use alloc::vec::Vec;
 
 /// Errros that may occur during image decoding.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum DecodeError {
     /// Buffer does not contain enough encoded data.
     NotEnoughData,
 
     /// Encoded header contains invalid magic value.\
-    /// First four bytes must contain `b"qoif"`.\
+    /// First four bytes must contain `b"qoif"` or custom magic when specified.\
     /// This usually indicates that buffer does not contain QOI image.
     InvalidMagic,
 
@@ -28,6 +32,37 @@ pub enum DecodeError {
 
     /// Output buffer is too small to fit decoded image.
     OutputIsTooSmall,
+
+    /// Extended header has unknown version.
+    UnsupportedVersion,
+
+    /// Extended header requires unknown extensions.
+    UnsupportedCapabilities,
+
+    /// Metadata sidecar is malformed.
+    InvalidMetadata,
+
+    /// Requested region does not fit into the image.
+    InvalidRegion,
+
+    /// Encoded image is not terminated with valid end marker.
+    InvalidEndMarker,
+
+    /// Data URI is malformed or does not contain base64 encoded QOI image.
+    InvalidDataUri,
+
+    /// Band index does not match encoded image.
+    InvalidBandIndex,
+
+    /// Size of the decoded image does not fit into `usize`.\
+    /// Such image cannot be decoded into memory on this target.
+    TooLarge,
+
+    /// Image exceeds specified [`Limits`].
+    LimitsExceeded,
+
+    /// Encoded image is followed by unexpected bytes after the end marker.
+    TrailingData,
 }
 
 impl Display for DecodeError {
@@ -46,6 +81,97 @@ impl Display for DecodeError {
             DecodeError::OutputIsTooSmall => {
                 f.write_str("Output buffer is too small to fit decoded image")
             }
+            DecodeError::UnsupportedVersion => f.write_str("Extended header has unknown version"),
+            DecodeError::UnsupportedCapabilities => {
+                f.write_str("Extended header requires unknown extensions")
+            }
+            DecodeError::InvalidMetadata => f.write_str("Metadata sidecar is malformed"),
+            DecodeError::InvalidRegion => {
+                f.write_str("Requested region does not fit into the image")
+            }
+            DecodeError::InvalidEndMarker => {
+                f.write_str("Encoded image is not terminated with valid end marker")
+            }
+            DecodeError::InvalidDataUri => {
+                f.write_str("Data URI is malformed or does not contain base64 encoded QOI image")
+            }
+            DecodeError::InvalidBandIndex => f.write_str("Band index does not match encoded image"),
+            DecodeError::TooLarge => {
+                f.write_str("Size of the decoded image does not fit into usize")
+            }
+            DecodeError::LimitsExceeded => f.write_str("Image exceeds specified limits"),
+            DecodeError::TrailingData => {
+                f.write_str("Encoded image is followed by unexpected bytes after the end marker")
+            }
+        }
+    }
+}
+
+impl DecodeError {
+    /// Returns stable numeric code of the error.\
+    /// Codes never change between versions and `0` is never used,
+    /// so it can denote success at FFI boundaries.
+    ///
+    /// | Code | Error                                    |
+    /// |------|------------------------------------------|
+    /// | 1    | [`DecodeError::NotEnoughData`]           |
+    /// | 2    | [`DecodeError::InvalidMagic`]            |
+    /// | 3    | [`DecodeError::InvalidChannelsValue`]    |
+    /// | 4    | [`DecodeError::InvalidColorSpaceValue`]  |
+    /// | 5    | [`DecodeError::OutputIsTooSmall`]        |
+    /// | 6    | [`DecodeError::UnsupportedVersion`]      |
+    /// | 7    | [`DecodeError::UnsupportedCapabilities`] |
+    /// | 8    | [`DecodeError::InvalidMetadata`]         |
+    /// | 9    | [`DecodeError::InvalidRegion`]           |
+    /// | 10   | [`DecodeError::InvalidEndMarker`]        |
+    /// | 11   | [`DecodeError::InvalidDataUri`]          |
+    /// | 12   | [`DecodeError::InvalidBandIndex`]        |
+    /// | 13   | [`DecodeError::TooLarge`]                |
+    /// | 14   | [`DecodeError::LimitsExceeded`]          |
+    /// | 15   | [`DecodeError::TrailingData`]            |
+    #[inline]
+    pub const fn code(&self) -> u32 {
+        match self {
+            DecodeError::NotEnoughData => 1,
+            DecodeError::InvalidMagic => 2,
+            DecodeError::InvalidChannelsValue => 3,
+            DecodeError::InvalidColorSpaceValue => 4,
+            DecodeError::OutputIsTooSmall => 5,
+            DecodeError::UnsupportedVersion => 6,
+            DecodeError::UnsupportedCapabilities => 7,
+            DecodeError::InvalidMetadata => 8,
+            DecodeError::InvalidRegion => 9,
+            DecodeError::InvalidEndMarker => 10,
+            DecodeError::InvalidDataUri => 11,
+            DecodeError::InvalidBandIndex => 12,
+            DecodeError::TooLarge => 13,
+            DecodeError::LimitsExceeded => 14,
+            DecodeError::TrailingData => 15,
+        }
+    }
+
+    /// Returns error with specified numeric code.\
+    /// Returns `None` if code is unknown.\
+    /// See [`DecodeError::code`] for the list of codes.
+    #[inline]
+    pub const fn from_code(code: u32) -> Option<Self> {
+        match code {
+            1 => Some(DecodeError::NotEnoughData),
+            2 => Some(DecodeError::InvalidMagic),
+            3 => Some(DecodeError::InvalidChannelsValue),
+            4 => Some(DecodeError::InvalidColorSpaceValue),
+            5 => Some(DecodeError::OutputIsTooSmall),
+            6 => Some(DecodeError::UnsupportedVersion),
+            7 => Some(DecodeError::UnsupportedCapabilities),
+            8 => Some(DecodeError::InvalidMetadata),
+            9 => Some(DecodeError::InvalidRegion),
+            10 => Some(DecodeError::InvalidEndMarker),
+            11 => Some(DecodeError::InvalidDataUri),
+            12 => Some(DecodeError::InvalidBandIndex),
+            13 => Some(DecodeError::TooLarge),
+            14 => Some(DecodeError::LimitsExceeded),
+            15 => Some(DecodeError::TrailingData),
+            _ => None,
         }
     }
 }
@@ -53,34 +179,89 @@ impl Display for DecodeError {
 #[cfg(feature = "std")]
 impl std::error::Error for DecodeError {}
 
+/// Progress of [`Qoi::decode_range`] call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RangeProgress {
+    /// Number of input bytes consumed.
+    pub bytes: usize,
+
+    /// Number of pixels written.
+    pub pixels: usize,
+
+    /// Number of pixels left in the pending run.
+    pub run: usize,
+}
+
+impl TryFrom<&[u8]> for Qoi {
+    type Error = DecodeError;
+
+    /// Reads header from encoded QOI image.\
+    /// Same as [`Qoi::decode_header`].
+    #[inline]
+    fn try_from(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode_header(bytes)
+    }
+}
+
 impl Qoi {
-    /// Returns bytes size for the decoded image.
+    /// Returns bytes size for the decoded image.\
+    /// Wraps around if size does not fit into `usize`,
+    /// use [`Qoi::checked_decoded_size`] for images from untrusted sources.
     #[inline]
-    pub fn decoded_size(&self) -> usize {
+    pub const fn decoded_size(&self) -> usize {
         self.width as usize * self.height as usize * self.colors.channels()
     }
 
-    /// Reads header from encoded QOI image.\
-    /// Returned header can be analyzed before proceeding parsing with [`Qoi::decode_skip_header`].
-    pub fn decode_header(bytes: &[u8]) -> Result<Self, DecodeError> {
-        if bytes.len() < QOI_HEADER_SIZE {
-            return Err(DecodeError::NotEnoughData);
+    /// Returns bytes size for the decoded image.\
+    /// Returns `None` if size does not fit into `usize`,
+    /// which is possible for huge images declared in header, especially on 32-bit targets.
+    #[inline]
+    pub const fn checked_decoded_size(&self) -> Option<usize> {
+        match (self.width as usize).checked_mul(self.height as usize) {
+            None => None,
+            Some(px_count) => px_count.checked_mul(self.colors.channels()),
         }
+    }
 
-        let magic = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
-        if magic != QOI_MAGIC {
-            return Err(DecodeError::InvalidMagic);
+    /// Returns number of pixels in the image.\
+    /// Fails with [`DecodeError::TooLarge`] if it does not fit into `usize`.
+    #[inline]
+    pub(crate) const fn pixel_count(&self) -> Result<usize, DecodeError> {
+        match (self.width as usize).checked_mul(self.height as usize) {
+            None => Err(DecodeError::TooLarge),
+            Some(px_count) => Ok(px_count),
         }
+    }
 
-        let w = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
-        let h = u32::from_be_bytes(bytes[8..12].try_into().unwrap());
+    /// Reads header from encoded QOI image.\
+    /// Returned header can be analyzed before proceeding parsing with [`Qoi::decode_skip_header`].\
+    /// Usable in const contexts, e.g. to validate images embedded with `include_bytes!` at compile time.
+    #[inline]
+    pub const fn decode_header(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode_header_with_magic(bytes, QOI_MAGIC.to_be_bytes())
+    }
 
-        let channels = bytes[12];
-        let colors = bytes[13];
+    /// Reads header from encoded QOI image with custom magic value.\
+    /// Allows to validate QOI images embedded into private containers that use their own magic,
+    /// see [`Qoi::encode_with_magic`].
+    pub const fn decode_header_with_magic(
+        bytes: &[u8],
+        magic: [u8; 4],
+    ) -> Result<Self, DecodeError> {
+        let [m0, m1, m2, m3, w0, w1, w2, w3, h0, h1, h2, h3, channels, colors] =
+            match bytes.first_chunk::<QOI_HEADER_SIZE>() {
+                None => return Err(DecodeError::NotEnoughData),
+                Some(header) => *header,
+            };
+
+        // Compared as integers, array comparison is not available in `const fn`.
+        if u32::from_ne_bytes([m0, m1, m2, m3]) != u32::from_ne_bytes(magic) {
+            return Err(DecodeError::InvalidMagic);
+        }
 
         Ok(Qoi {
-            width: w,
-            height: h,
+            width: u32::from_be_bytes([w0, w1, w2, w3]),
+            height: u32::from_be_bytes([h0, h1, h2, h3]),
             colors: match (channels, colors) {
                 (3, 0) => Colors::Srgb,
                 (4, 0) => Colors::SrgbLinA,
@@ -92,6 +273,35 @@ impl Qoi {
         })
     }
 
+    /// Returns size of the QOI image at the start of `bytes` including header and end marker.\
+    /// Ops are scanned without producing pixels.
+    /// Bytes after the end marker are ignored,
+    /// which allows to find boundaries of images stored back-to-back.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encoded_len(bytes: &[u8]) -> Result<usize, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let mut left = qoi.width as u64 * qoi.height as u64;
+        let mut pos = QOI_HEADER_SIZE;
+
+        while left > 0 {
+            let b1 = match bytes.get(pos) {
+                None => return Err(DecodeError::NotEnoughData),
+                Some(&b1) => b1,
+            };
+            pos += op_size(b1);
+            left = left.saturating_sub(op_pixels(b1) as u64);
+        }
+
+        match bytes.get(pos..pos + QOI_PADDING) {
+            None => Err(DecodeError::NotEnoughData),
+            Some(padding) if *padding != QOI_END_MARKER => Err(DecodeError::InvalidEndMarker),
+            Some(_) => Ok(pos + QOI_PADDING),
+        }
+    }
+
     /// Decode a QOI image from bytes slice.\
     /// Decoded raw RGB or RGBA pixels are written into `output` slice.
     ///
@@ -104,6 +314,23 @@ impl Qoi {
         Ok(qoi)
     }
 
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw RGB or RGBA pixels are written into array on the stack, which suits tiny images like icons and cursors
+    /// without requiring `alloc`.\
+    /// `MAX` can be computed from constant image description with [`Qoi::decoded_size`].
+    ///
+    /// On success this function returns `Ok((qoi, array, size))` with `qoi` describing image dimensions and color space
+    /// and first `size` bytes of `array` containing raw pixels data.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_array<const MAX: usize>(
+        bytes: &[u8],
+    ) -> Result<(Self, [u8; MAX], usize), DecodeError> {
+        let mut output = [0; MAX];
+        let qoi = Self::decode(bytes, &mut output)?;
+        Ok((qoi, output, qoi.decoded_size()))
+    }
+
     /// Decode a QOI image from bytes slice.\
     /// `bytes` does not include QOI header. Uses provided `Qoi` value instead.\
     /// Decoded raw RGB or RGBA (depending on `self.colors` value) pixels are written into `output` slice.
@@ -111,12 +338,30 @@ impl Qoi {
     /// On success this function returns `Ok(())`.\
     /// On failure this function returns `Err(err)` with `err` describing cause of the error.
     #[inline]
-    pub fn decode_skip_header(&self, bytes: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
+    pub fn decode_skip_header(
+        &self,
+        mut bytes: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), DecodeError> {
+        self.decode_skip_header_from(&mut bytes, output)
+    }
+
+    /// Decode all pixels of the image from `source` into `output` slice.\
+    /// Decoded bytes are consumed from `source`, leaving it right after the last op of the image.
+    #[inline]
+    pub(crate) fn decode_skip_header_from(
+        &self,
+        source: &mut &[u8],
+        output: &mut [u8],
+    ) -> Result<(), DecodeError> {
         if self.width == 0 || self.height == 0 {
             return Ok(());
         }
 
-        let px_len = self.decoded_size();
+        let px_len = match self.checked_decoded_size() {
+            None => return Err(DecodeError::TooLarge),
+            Some(px_len) => px_len,
+        };
 
         let output = match output.get_mut(..px_len) {
             None => return Err(DecodeError::OutputIsTooSmall),
@@ -125,20 +370,20 @@ impl Qoi {
 
         match self.colors.has_alpha() {
             true => {
-                Self::decode_range::<4>(
+                Self::decode_range_from_source::<4>(
                     &mut [Pixel::new(); 64],
                     &mut Pixel::new_opaque(),
                     &mut 0,
-                    bytes,
+                    source,
                     output,
                 )?;
             }
             false => {
-                Self::decode_range::<3>(
+                Self::decode_range_from_source::<3>(
                     &mut [Pixel::new(); 64],
                     &mut Pixel::new_opaque(),
                     &mut 0,
-                    bytes,
+                    source,
                     output,
                 )?;
             }
@@ -146,7 +391,18 @@ impl Qoi {
         Ok(())
     }
 
-    /// Decode range of pixels into pixels slice.
+    /// Decode range of pixels into pixels slice.\
+    /// `N` is number of channels in `pixels`, with `[u8; N]` as the [`Pixel`] type.\
+    /// `index`, `ppx` and `prun` hold decoder state between calls.\
+    /// Trailing bytes of `pixels` that do not form a whole pixel are left untouched.
+    ///
+    /// Decoding stops when `pixels` slice is filled or when `bytes` end,
+    /// possibly with incomplete op that is left unconsumed.
+    /// Decoding can be continued from there with more bytes.
+    ///
+    /// On success this function returns `Ok(progress)` with number of bytes consumed,
+    /// pixels written and pixels left in the pending run.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
     #[inline]
     pub fn decode_range<const N: usize>(
         index: &mut [[u8; N]; 64],
@@ -154,18 +410,13 @@ impl Qoi {
         prun: &mut usize,
         bytes: &[u8],
         pixels: &mut [u8],
-    ) -> Result<usize, DecodeError>
+    ) -> Result<RangeProgress, DecodeError>
     where
         [u8; N]: Pixel,
     {
-        assert_eq!(pixels.len() % N, 0);
+        let total = pixels.len() / N;
 
-        // let (mut pixels, rem) = pixels.as_chunks_mut::<N>();
-        // let mut pixels = pixels.chunks_exact_mut(N).map(cast_pixel::<N>);
-
-        let mut pixels = bytemuck::cast_slice_mut(pixels);
-
-        // assert!(rem.is_empty());
+        let (mut pixels, _) = pixels.as_chunks_mut::<N>();
 
         let mut px = *ppx;
 
@@ -177,24 +428,44 @@ impl Qoi {
             // pixels.by_ref().take(*prun).for_each(|pixel| *pixel = px);
 
             pixels = tail;
-            head.fill(px);
+            fill_run(head, px);
 
             if pixels.is_empty() {
                 cold();
                 *prun -= head.len();
-                return Ok(0);
+                return Ok(RangeProgress {
+                    bytes: 0,
+                    pixels: total,
+                    run: *prun,
+                });
             } else {
                 *prun = 0;
             }
         }
 
         let mut rest = bytes;
+        let mut unfilled = 0;
+
+        #[cfg(feature = "unsafe-perf")]
+        {
+            let (consumed, written) =
+                crate::unchecked::decode_ops::<N>(index, &mut px, rest, pixels);
+            rest = rest.get(consumed..).unwrap_or_default();
+            pixels = core::mem::take(&mut pixels)
+                .get_mut(written..)
+                .unwrap_or_default();
+        }
 
         loop {
             match pixels {
                 [out, tail @ ..] => {
                     // Some(out) => {
                     pixels = tail;
+
+                    // Arms compile into a tree of compares on the first byte.
+                    // Jump table keyed on the 2-bit tag was measured slower for every kind of image,
+                    // and checking `QOI_OP_LUMA` first speeds up luma-heavy images
+                    // by less than it slows down index-heavy ones.
                     match rest {
                         [b1 @ 0b00000000..=0b00111111, tail @ ..] => {
                             px = index[*b1 as usize];
@@ -226,6 +497,16 @@ impl Qoi {
                             // px[2] = *b4;
 
                             rest = tail;
+
+                            index[px.hash() as usize] = px;
+                            *out = px;
+
+                            let count = decode_literals::<N, 4>(index, &mut px, rest, pixels);
+                            rest = rest.get(count * 4..).unwrap_or_default();
+                            pixels = core::mem::take(&mut pixels)
+                                .get_mut(count..)
+                                .unwrap_or_default();
+                            continue;
                         }
                         [0b11111111, b2, b3, b4, _b5, tail @ ..] if N == 3 => {
                             cold();
@@ -245,12 +526,22 @@ impl Qoi {
                             // px[3] = *b5;
 
                             rest = tail;
+
+                            index[px.hash() as usize] = px;
+                            *out = px;
+
+                            let count = decode_literals::<N, 5>(index, &mut px, rest, pixels);
+                            rest = rest.get(count * 5..).unwrap_or_default();
+                            pixels = core::mem::take(&mut pixels)
+                                .get_mut(count..)
+                                .unwrap_or_default();
+                            continue;
                         }
                         [b1 @ 0b11000000..=0b11111101, dtail @ ..] => {
                             *out = px;
                             let run = *b1 as usize & 0x3f;
-                            let (head, tail) = pixels.split_at_mut(run);
-                            head.fill(px);
+                            let (head, tail) = pixels.split_at_mut(run.min(pixels.len()));
+                            fill_run(head, px);
                             pixels = tail;
                             rest = dtail;
 
@@ -265,11 +556,10 @@ impl Qoi {
                             continue;
                         }
                         _ => {
-                            // if unlikely(rest.len() < QOI_PADDING) {
-                            return Err(DecodeError::NotEnoughData);
-                            // }
-                            // Unreachable arm due to length check above.
-                            // unreachable();
+                            // Bytes ended, possibly with incomplete op.
+                            cold();
+                            unfilled = pixels.len() + 1;
+                            break;
                         }
                     }
                     //     }
@@ -292,7 +582,94 @@ impl Qoi {
 
         *ppx = px;
 
-        Ok(bytes.len() - rest.len())
+        Ok(RangeProgress {
+            bytes: bytes.len() - rest.len(),
+            pixels: total - unfilled,
+            run: *prun,
+        })
+    }
+
+    /// Decode range of pixels from `source` into pixels slice.\
+    /// Decoded bytes are consumed from `source`.\
+    /// Fails with [`DecodeError::NotEnoughData`] if `source` ends before pixels slice is filled.
+    #[inline]
+    pub(crate) fn decode_range_from_source<const N: usize>(
+        index: &mut [[u8; N]; 64],
+        px: &mut [u8; N],
+        run: &mut usize,
+        source: &mut impl ByteSource,
+        pixels: &mut [u8],
+    ) -> Result<(), DecodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let progress = Self::decode_range::<N>(index, px, run, source.bytes(), pixels)?;
+        source.consume(progress.bytes);
+
+        if progress.pixels < pixels.len() / N {
+            return Err(DecodeError::NotEnoughData);
+        }
+        Ok(())
+    }
+
+    /// Decode all pixels of the image in small chunks.\
+    /// `bytes` does not include QOI header.\
+    /// Each chunk is decoded into temporary buffer on the stack and passed to `f`.
+    #[inline]
+    pub(crate) fn decode_chunks<const N: usize>(
+        &self,
+        mut bytes: &[u8],
+        f: impl FnMut(&[[u8; N]]),
+    ) -> Result<(), DecodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        self.decode_chunks_from(&mut bytes, f)
+    }
+
+    /// Decode all pixels of the image in small chunks consuming decoded bytes from `source`.\
+    /// Each chunk is decoded into temporary buffer on the stack and passed to `f`.
+    pub(crate) fn decode_chunks_from<const N: usize>(
+        &self,
+        source: &mut &[u8],
+        mut f: impl FnMut(&[[u8; N]]),
+    ) -> Result<(), DecodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        const CHUNK: usize = 256;
+
+        let mut index = [Pixel::new(); 64];
+        let mut px = Pixel::new_opaque();
+        let mut run = 0;
+
+        let mut buffer = [[0; N]; CHUNK];
+        let mut left = self.pixel_count()?;
+
+        while left > 0 {
+            let chunk = &mut buffer[..left.min(CHUNK)];
+            Self::decode_range_from_source::<N>(
+                &mut index,
+                &mut px,
+                &mut run,
+                source,
+                chunk.as_flattened_mut(),
+            )?;
+            left -= chunk.len();
+            f(chunk);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that `bytes` start with the end marker.\
+    /// Fails with [`DecodeError::InvalidEndMarker`] if end marker is missing or malformed.
+    #[inline]
+    pub(crate) fn check_end_marker(bytes: &[u8]) -> Result<(), DecodeError> {
+        match bytes.get(..QOI_PADDING) {
+            Some(padding) if *padding == QOI_END_MARKER => Ok(()),
+            _ => Err(DecodeError::InvalidEndMarker),
+        }
     }
 
     /// Decode a QOI image from bytes slice.\
@@ -303,11 +680,109 @@ impl Qoi {
     #[cfg(feature = "alloc")]
     #[inline]
     pub fn decode_alloc(bytes: &[u8]) -> Result<(Self, Vec<u8>), DecodeError> {
+        let mut output = Vec::new();
+        let qoi = Self::decode_into_vec(bytes, &mut output)?;
+        Ok((qoi, output))
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw RGB or RGBA pixels replace content of `output`, its capacity is reused,
+    /// so decoding frames of the same size repeatedly does not allocate after the first one.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space and `output` containing raw pixels data.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    /// `output` is left empty.
+    #[cfg(feature = "alloc")]
+    pub fn decode_into_vec(bytes: &[u8], output: &mut Vec<u8>) -> Result<Self, DecodeError> {
+        output.clear();
+
         let qoi = Self::decode_header(bytes)?;
 
-        let size = qoi.decoded_size();
-        let mut output = vec![0; size];
-        let qoi = Self::decode(bytes, &mut output)?;
-        Ok((qoi, output))
+        // Pixels are appended chunk by chunk into reserved capacity,
+        // so output is never zero-filled.
+        match qoi.checked_decoded_size() {
+            None => return Err(DecodeError::TooLarge),
+            Some(size) => output.reserve(size),
+        }
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let result = match qoi.colors.has_alpha() {
+            true => qoi.decode_chunks::<4>(bytes, |pixels| {
+                output.extend_from_slice(pixels.as_flattened())
+            }),
+            false => qoi.decode_chunks::<3>(bytes, |pixels| {
+                output.extend_from_slice(pixels.as_flattened())
+            }),
+        };
+
+        if let Err(err) = result {
+            output.clear();
+            return Err(err);
+        }
+
+        Ok(qoi)
+    }
+}
+
+/// Decodes sequence of literal ops of `L` bytes each, `QOI_OP_RGB` if `L` is `4` or `QOI_OP_RGBA` if `L` is `5`.\
+/// Photos are mostly encoded as long stretches of such ops.
+/// Pixels are copied in a tight loop without op dispatch
+/// and index is updated afterwards in a separate pass.
+///
+/// Returns number of decoded pixels.
+#[inline]
+fn decode_literals<const N: usize, const L: usize>(
+    index: &mut [[u8; N]; 64],
+    px: &mut [u8; N],
+    bytes: &[u8],
+    pixels: &mut [[u8; N]],
+) -> usize
+where
+    [u8; N]: Pixel,
+{
+    let tag = match L {
+        4 => QOI_OP_RGB,
+        _ => QOI_OP_RGBA,
+    };
+
+    let mut count = 0;
+    for (out, op) in pixels.iter_mut().zip(bytes.chunks_exact(L)) {
+        if op[0] != tag {
+            break;
+        }
+        match L {
+            4 => px.set_rgb(op[1], op[2], op[3]),
+            _ => px.set_rgba(op[1], op[2], op[3], op[4]),
+        }
+        *out = *px;
+        count += 1;
+    }
+
+    // Literals never refer to the index, so updates are deferred until the sequence ends.
+    for px in &pixels[..count.min(pixels.len())] {
+        index[px.hash() as usize] = *px;
+    }
+
+    count
+}
+
+/// Number of pixels written at once by [`fill_run`].
+const RUN_LANES: usize = 16;
+
+/// Fills `pixels` with `px`.\
+/// Whole blocks of [`RUN_LANES`] pixels are copied from splatted pattern,
+/// which compiles to few wide stores instead of a store per pixel.
+#[inline]
+fn fill_run<const N: usize>(pixels: &mut [[u8; N]], px: [u8; N]) {
+    let splat = [px; RUN_LANES];
+
+    let mut blocks = pixels.chunks_exact_mut(RUN_LANES);
+    for block in &mut blocks {
+        block.copy_from_slice(&splat);
+    }
+
+    let tail = blocks.into_remainder();
+    if let Some(splat) = splat.get(..tail.len()) {
+        tail.copy_from_slice(splat);
     }
 }
@@ -0,0 +1,216 @@
+use super::*;
+
+use crate::ops::{op_pixels, op_size};
+
+/// Progress of [`Decoder::feed`] call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Progress {
+    /// Number of input bytes consumed.
+    pub bytes: usize,
+
+    /// Number of pixels written into output.
+    pub pixels: usize,
+}
+
+/// Stateful decoder that holds color index, previous pixel and pending run between calls.
+///
+/// [`Decoder::feed`] accepts encoded image in fragments of arbitrary size.
+/// Incomplete header and ops at the end of a fragment are buffered internally
+/// and decoding resumes when more data arrives.\
+/// [`Decoder::decode_into`] decodes from buffers that are known to hold enough data.
+#[derive(Clone, Copy, Debug)]
+pub struct Decoder {
+    header: [u8; QOI_HEADER_SIZE],
+    header_len: usize,
+    qoi: Option<Qoi>,
+    state: DecState,
+    pending: [u8; 5],
+    pending_len: usize,
+}
+
+impl Default for Decoder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    /// Returns new decoder expecting image header.
+    #[inline]
+    pub const fn new() -> Self {
+        Decoder {
+            header: [0; QOI_HEADER_SIZE],
+            header_len: 0,
+            qoi: None,
+            state: DecState::new(),
+            pending: [0; 5],
+            pending_len: 0,
+        }
+    }
+
+    /// Returns new decoder for image data without header.\
+    /// Uses provided `Qoi` value instead.
+    #[inline]
+    pub const fn with_header(qoi: Qoi) -> Self {
+        Decoder {
+            header: [0; QOI_HEADER_SIZE],
+            header_len: QOI_HEADER_SIZE,
+            qoi: Some(qoi),
+            state: DecState::new(),
+            pending: [0; 5],
+            pending_len: 0,
+        }
+    }
+
+    /// Returns image header if it was received.
+    #[inline]
+    pub fn qoi(&self) -> Option<Qoi> {
+        self.qoi
+    }
+
+    /// Returns number of pixels decoded so far.
+    #[inline]
+    pub fn pixels(&self) -> u64 {
+        self.state.pixels()
+    }
+
+    /// Returns `true` if all pixels of the image are decoded.
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        match self.qoi {
+            None => false,
+            Some(qoi) => self.state.pixels() == qoi.width as u64 * qoi.height as u64,
+        }
+    }
+
+    /// Feed next fragment of encoded image.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice,
+    /// which must start at the next pixel to decode.
+    ///
+    /// Consumes as much of `bytes` as possible.
+    /// Only stops early when `output` is full or all pixels are decoded,
+    /// in which case remaining bytes should be fed again with the next output slice.\
+    /// Call [`Decoder::qoi`] after header is received to find image size.
+    ///
+    /// On success this function returns `Ok(progress)` with number of bytes consumed and pixels written.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn feed(&mut self, bytes: &[u8], output: &mut [u8]) -> Result<Progress, DecodeError> {
+        let mut progress = Progress::default();
+        let mut bytes = bytes;
+
+        let qoi = match self.qoi {
+            Some(qoi) => qoi,
+            None => {
+                let len = (QOI_HEADER_SIZE - self.header_len).min(bytes.len());
+                self.header[self.header_len..][..len].copy_from_slice(&bytes[..len]);
+                self.header_len += len;
+                bytes = &bytes[len..];
+                progress.bytes += len;
+
+                if self.header_len < QOI_HEADER_SIZE {
+                    return Ok(progress);
+                }
+
+                let qoi = Qoi::decode_header(&self.header)?;
+                self.qoi = Some(qoi);
+                qoi
+            }
+        };
+
+        let channels = qoi.colors.channels();
+        let left = qoi.width as u64 * qoi.height as u64 - self.state.pixels();
+        let mut want = ((output.len() / channels) as u64).min(left) as usize;
+        let mut output = &mut output[..want * channels];
+
+        if left == 0 {
+            return Ok(progress);
+        }
+
+        if self.pending_len > 0 {
+            let size = op_size(self.pending[0]);
+            let len = (size - self.pending_len).min(bytes.len());
+            self.pending[self.pending_len..][..len].copy_from_slice(&bytes[..len]);
+            self.pending_len += len;
+            bytes = &bytes[len..];
+            progress.bytes += len;
+
+            if self.pending_len < size || want == 0 {
+                return Ok(progress);
+            }
+
+            let pending = self.pending;
+            qoi.decode_with_state(&mut self.state, &pending[..size], &mut output[..channels])?;
+            self.pending_len = 0;
+
+            output = &mut output[channels..];
+            want -= 1;
+            progress.pixels += 1;
+        }
+
+        // Find complete ops that are enough to fill the output.
+        let mut end = 0;
+        let mut available = self.state.run();
+        while available < want && end < bytes.len() {
+            let size = op_size(bytes[end]);
+            if end + size > bytes.len() {
+                break;
+            }
+            available += op_pixels(bytes[end]);
+            end += size;
+        }
+
+        let count = available.min(want);
+        let offset = self.state.offset();
+        qoi.decode_with_state(
+            &mut self.state,
+            &bytes[..end],
+            &mut output[..count * channels],
+        )?;
+        let consumed = (self.state.offset() - offset) as usize;
+
+        progress.bytes += consumed;
+        progress.pixels += count;
+
+        if count < want && end < bytes.len() {
+            // Output is not full yet, buffer incomplete op.
+            let tail = &bytes[end..];
+            self.pending[..tail.len()].copy_from_slice(tail);
+            self.pending_len = tail.len();
+            progress.bytes += tail.len();
+        }
+
+        Ok(progress)
+    }
+
+    /// Decode next pixels from `bytes` that continue the encoded image.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice,
+    /// which must start at the next pixel to decode.
+    ///
+    /// Unlike [`Decoder::feed`], `bytes` must contain enough data to fill `output`
+    /// or to finish the image, whichever comes first.
+    /// Otherwise this function fails with [`DecodeError::NotEnoughData`] and decoder is left unchanged,
+    /// so it can be retried with more data.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the consumed data.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_into(&mut self, bytes: &[u8], output: &mut [u8]) -> Result<usize, DecodeError> {
+        let mut decoder = *self;
+        let progress = decoder.feed(bytes, output)?;
+
+        let qoi = match decoder.qoi {
+            None => return Err(DecodeError::NotEnoughData),
+            Some(qoi) => qoi,
+        };
+
+        let left = qoi.width as u64 * qoi.height as u64 - self.state.pixels();
+        let want = ((output.len() / qoi.colors.channels()) as u64).min(left);
+
+        if (progress.pixels as u64) < want {
+            return Err(DecodeError::NotEnoughData);
+        }
+
+        *self = decoder;
+        Ok(progress.bytes)
+    }
+}
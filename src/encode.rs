@@ -3,14 +3,29 @@ use super::*;
 #[cfg(feature = "alloc")]
 use alloc::{vec, vec::Vec};
 
+#[cfg(feature = "alloc")]
+use crate::bytes::VecSink;
+
+use crate::bytes::{ByteSink, SliceSink};
+
 /// Errors that may occur during image encoding.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum EncodeError {
     /// Pixels buffer is too small for the image.
     NotEnoughPixelData,
 
     /// Output buffer is too small to fit encoded image.
     OutputIsTooSmall,
+
+    /// Op cannot appear in valid QOI stream.
+    InvalidOp,
+
+    /// Metadata record cannot be encoded, e.g. frame name is longer than 255 bytes.
+    InvalidMetadata,
+
+    /// Image size does not fit into `usize` on this target.
+    TooLarge,
 }
 
 impl Display for EncodeError {
@@ -20,6 +35,48 @@ impl Display for EncodeError {
             EncodeError::OutputIsTooSmall => {
                 f.write_str("Output buffer is too small to fit encoded image")
             }
+            EncodeError::InvalidOp => f.write_str("Op cannot appear in valid QOI stream"),
+            EncodeError::InvalidMetadata => f.write_str("Metadata record cannot be encoded"),
+            EncodeError::TooLarge => f.write_str("Image is too large for this target"),
+        }
+    }
+}
+
+impl EncodeError {
+    /// Returns stable numeric code of the error.\
+    /// Codes never change between versions and `0` is never used,
+    /// so it can denote success at FFI boundaries.
+    ///
+    /// | Code | Error                               |
+    /// |------|-------------------------------------|
+    /// | 1    | [`EncodeError::NotEnoughPixelData`] |
+    /// | 2    | [`EncodeError::OutputIsTooSmall`]   |
+    /// | 3    | [`EncodeError::InvalidOp`]          |
+    /// | 4    | [`EncodeError::InvalidMetadata`]    |
+    /// | 5    | [`EncodeError::TooLarge`]           |
+    #[inline]
+    pub const fn code(&self) -> u32 {
+        match self {
+            EncodeError::NotEnoughPixelData => 1,
+            EncodeError::OutputIsTooSmall => 2,
+            EncodeError::InvalidOp => 3,
+            EncodeError::InvalidMetadata => 4,
+            EncodeError::TooLarge => 5,
+        }
+    }
+
+    /// Returns error with specified numeric code.\
+    /// Returns `None` if code is unknown.\
+    /// See [`EncodeError::code`] for the list of codes.
+    #[inline]
+    pub const fn from_code(code: u32) -> Option<Self> {
+        match code {
+            1 => Some(EncodeError::NotEnoughPixelData),
+            2 => Some(EncodeError::OutputIsTooSmall),
+            3 => Some(EncodeError::InvalidOp),
+            4 => Some(EncodeError::InvalidMetadata),
+            5 => Some(EncodeError::TooLarge),
+            _ => None,
         }
     }
 }
@@ -28,6 +85,37 @@ impl Display for EncodeError {
 impl std::error::Error for EncodeError {}
 
 impl Qoi {
+    /// Returns number of elements in pixels buffer of the image with `channels` elements per pixel.\
+    /// Fails with [`EncodeError::TooLarge`] if it does not fit into `usize`.
+    #[inline]
+    pub(crate) const fn pixels_len(&self, channels: usize) -> Result<usize, EncodeError> {
+        match (self.width as usize).checked_mul(self.height as usize) {
+            Some(px_count) => match px_count.checked_mul(channels) {
+                Some(len) => Ok(len),
+                None => Err(EncodeError::TooLarge),
+            },
+            None => Err(EncodeError::TooLarge),
+        }
+    }
+
+    /// Returns pixels of the image from the start of `pixels` with `channels` elements per pixel.\
+    /// Fails with [`EncodeError::TooLarge`] if size of the image does not fit into `usize`
+    /// and with [`EncodeError::NotEnoughPixelData`] if `pixels` is too small for the image.
+    #[inline]
+    pub(crate) fn image_pixels<'a, T>(
+        &self,
+        pixels: &'a [T],
+        channels: usize,
+    ) -> Result<&'a [T], EncodeError> {
+        match pixels.get(..self.pixels_len(channels)?) {
+            None => {
+                cold();
+                Err(EncodeError::NotEnoughPixelData)
+            }
+            Some(pixels) => Ok(pixels),
+        }
+    }
+
     /// Encode raw RGB or RGBA pixels into a QOI image.\
     /// Encoded image is written into `output` slice.
     ///
@@ -35,92 +123,298 @@ impl Qoi {
     /// On failure this function returns `Err(err)` with `err` describing cause of the error.
     #[inline]
     pub fn encode(&self, pixels: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
-        if output.len() <= QOI_HEADER_SIZE {
-            return Err(EncodeError::OutputIsTooSmall);
-        }
+        self.encode_with_magic(QOI_MAGIC.to_be_bytes(), pixels, output)
+    }
 
-        output[0..4].copy_from_slice(&QOI_MAGIC.to_be_bytes());
-        output[4..8].copy_from_slice(&self.width.to_be_bytes());
-        output[8..12].copy_from_slice(&self.height.to_be_bytes());
+    /// Encode raw RGB or RGBA pixels into a QOI image with custom magic value instead of `b"qoif"`.\
+    /// Allows applications to brand QOI images embedded into private containers.
+    /// Such images can be read with [`Qoi::decode_header_with_magic`] followed by [`Qoi::decode_skip_header`].
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_with_magic(
+        &self,
+        magic: [u8; 4],
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let mut sink = SliceSink::new(output);
+        self.write_header_to_sink(magic, &mut sink)?;
+        self.encode_to_sink(pixels, &mut sink, &EncodeOptions::DEFAULT)?;
+        Ok(sink.written())
+    }
 
-        let channels = match self.colors {
-            Colors::Rgb => {
-                output[12] = 3;
-                output[13] = 1;
-                3
-            }
-            Colors::Rgba => {
-                output[12] = 4;
-                output[13] = 1;
-                4
+    /// Encode QOI header.\
+    /// Header is written into first 14 bytes of `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub const fn encode_header(&self, output: &mut [u8]) -> Result<usize, EncodeError> {
+        match output.first_chunk_mut() {
+            None => Err(EncodeError::OutputIsTooSmall),
+            Some(output) => {
+                self.write_header(QOI_MAGIC.to_be_bytes(), output);
+                Ok(QOI_HEADER_SIZE)
             }
-            Colors::Srgb => {
-                output[12] = 3;
-                output[13] = 0;
-                3
+        }
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image without header.\
+    /// Encoded pixels followed by end marker are written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded data.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_skip_header(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let mut sink = SliceSink::new(output);
+        self.encode_to_sink(pixels, &mut sink, &EncodeOptions::DEFAULT)?;
+        Ok(sink.written())
+    }
+
+    /// Encode RGBA pixels produced by an iterator into a QOI image.\
+    /// Pixels are encoded as they are produced, without raw pixels buffer for the whole image.
+    /// Alpha channel is ignored if color space has no alpha.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_from_iter(
+        &self,
+        pixels: impl IntoIterator<Item = [u8; 4]>,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let mut sink = SliceSink::new(output);
+        self.write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)?;
+
+        let mut pixels = pixels.into_iter();
+
+        match self.colors.has_alpha() {
+            true => {
+                self.encode_chunks::<4>(&mut sink, |chunk| fill_from_iter(chunk, &mut pixels))?
             }
-            Colors::SrgbLinA => {
-                output[12] = 4;
-                output[13] = 0;
-                4
+            false => {
+                self.encode_chunks::<3>(&mut sink, |chunk| fill_from_iter(chunk, &mut pixels))?
             }
+        }
+
+        Ok(sink.written())
+    }
+
+    /// Encode grayscale pixels into a QOI image.\
+    /// Each byte of `pixels` is a gray value replicated into red, green and blue channels.\
+    /// Image is always encoded without alpha channel,
+    /// only color channels encoding is taken from `self.colors`.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_luma(&self, pixels: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+        let qoi = Qoi {
+            colors: self.colors.with_alpha(false),
+            ..*self
         };
 
-        let px_len = self.width as usize * self.height as usize * channels;
+        let pixels = qoi.image_pixels(pixels, 1)?;
 
-        let pixels = match pixels.get(..px_len) {
-            None => {
-                cold();
-                return Err(EncodeError::NotEnoughPixelData);
+        let mut sink = SliceSink::new(output);
+        qoi.write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)?;
+
+        let mut source = pixels.iter();
+
+        qoi.encode_chunks::<3>(&mut sink, |chunk| {
+            for (px, &l) in chunk.iter_mut().zip(source.by_ref()) {
+                *px = [l, l, l];
             }
-            Some(pixels) => pixels,
-        };
+            Ok(())
+        })?;
 
-        let size = match self.colors.has_alpha() {
-            true => Self::encode_range::<4>(
+        Ok(sink.written())
+    }
+
+    /// Encode raw RGB or RGBA pixels followed by end marker into `sink`.
+    pub(crate) fn encode_to_sink(
+        &self,
+        pixels: &[u8],
+        sink: &mut impl ByteSink,
+        options: &EncodeOptions,
+    ) -> Result<(), EncodeError> {
+        let pixels = self.image_pixels(pixels, self.colors.channels())?;
+
+        match self.colors.has_alpha() {
+            true => Self::encode_range_to_sink::<4>(
                 &mut [[0; 4]; 64],
                 &mut Pixel::new_opaque(),
                 &mut 0,
                 pixels,
-                &mut output[QOI_HEADER_SIZE..],
+                true,
+                sink,
+                options,
             )?,
-            false => Self::encode_range::<3>(
+            false => Self::encode_range_to_sink::<3>(
                 &mut [[0; 4]; 64],
                 &mut Pixel::new_opaque(),
                 &mut 0,
                 pixels,
-                &mut output[QOI_HEADER_SIZE..],
+                true,
+                sink,
+                options,
             )?,
+        }
+
+        Self::write_padding_to_sink(sink)
+    }
+
+    /// Writes header with specified magic value into `sink`.
+    pub(crate) fn write_header_to_sink(
+        &self,
+        magic: [u8; 4],
+        sink: &mut impl ByteSink,
+    ) -> Result<(), EncodeError> {
+        let mut header = [0; QOI_HEADER_SIZE];
+        self.write_header(magic, &mut header);
+        sink.write_bytes(&header)
+    }
+
+    /// Writes end marker into `sink`.
+    pub(crate) fn write_padding_to_sink(sink: &mut impl ByteSink) -> Result<(), EncodeError> {
+        let mut padding = [0; QOI_PADDING];
+        padding[QOI_PADDING - 1] = 1;
+        sink.write_bytes(&padding)
+    }
+
+    /// Encode QOI header into array.\
+    /// Usable in const contexts, e.g. to compute headers of images assembled at compile time.
+    #[inline]
+    pub const fn encode_header_array(&self) -> [u8; QOI_HEADER_SIZE] {
+        let mut header = [0; QOI_HEADER_SIZE];
+        self.write_header(QOI_MAGIC.to_be_bytes(), &mut header);
+        header
+    }
+
+    /// Writes header with specified magic value into `output`.
+    pub(crate) const fn write_header(&self, magic: [u8; 4], output: &mut [u8; QOI_HEADER_SIZE]) {
+        let [m0, m1, m2, m3] = magic;
+        let [w0, w1, w2, w3] = self.width.to_be_bytes();
+        let [h0, h1, h2, h3] = self.height.to_be_bytes();
+
+        let (channels, colorspace) = match self.colors {
+            Colors::Rgb => (3, 1),
+            Colors::Rgba => (4, 1),
+            Colors::Srgb => (3, 0),
+            Colors::SrgbLinA => (4, 0),
         };
 
-        if output.len() < size + QOI_PADDING + QOI_HEADER_SIZE {
-            return Err(EncodeError::OutputIsTooSmall);
+        *output = [
+            m0, m1, m2, m3, w0, w1, w2, w3, h0, h1, h2, h3, channels, colorspace,
+        ];
+    }
+
+    /// Encode range of pixels into output slice.\
+    /// `N` is number of channels in `pixels`, with `[u8; N]` as the [`Pixel`] type.\
+    /// Trailing bytes of `pixels` that do not form a whole pixel are ignored.\
+    /// Fails with [`EncodeError::InvalidOp`] if `run` is greater than `61`,
+    /// such value cannot be left by previous calls.
+    #[inline]
+    pub fn encode_range<const N: usize>(
+        index: &mut [[u8; 4]; 64],
+        px_prev: &mut [u8; N],
+        run: &mut usize,
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        if *run > 61 {
+            return Err(EncodeError::InvalidOp);
         }
 
-        output[QOI_HEADER_SIZE + size..][..QOI_PADDING - 1].fill(0);
-        output[QOI_HEADER_SIZE + size + QOI_PADDING - 1] = 1;
+        Self::encode_range_impl::<N>(index, px_prev, run, pixels, output, true)
+    }
 
-        Ok(size + QOI_PADDING + QOI_HEADER_SIZE)
+    /// Terminates stream encoded with [`Qoi::encode_range`] calls.\
+    /// Writes pending run, if any, followed by the end marker into `output` slice
+    /// and resets `run` to zero.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the written data.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn finish_range(run: &mut usize, output: &mut [u8]) -> Result<usize, EncodeError> {
+        if *run > 62 {
+            return Err(EncodeError::InvalidOp);
+        }
+
+        let size = (*run > 0) as usize;
+
+        let output = match output.get_mut(..size + QOI_PADDING) {
+            None => return Err(EncodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        if *run > 0 {
+            output[0] = QOI_OP_RUN | (*run - 1) as u8;
+        }
+
+        output[size..][..QOI_PADDING - 1].fill(0);
+        output[size + QOI_PADDING - 1] = 1;
+
+        *run = 0;
+        Ok(size + QOI_PADDING)
     }
 
-    /// Encode range of pixels into output slice.
+    /// Encode range of pixels into output slice.\
+    /// Pending run is written at the end of the range only if `flush` is `true`.
+    /// Otherwise it is left in `run` to be continued by the next range.
     #[inline]
-    pub fn encode_range<const N: usize>(
+    pub(crate) fn encode_range_impl<const N: usize>(
         index: &mut [[u8; 4]; 64],
         px_prev: &mut [u8; N],
         run: &mut usize,
         pixels: &[u8],
         output: &mut [u8],
+        flush: bool,
     ) -> Result<usize, EncodeError>
     where
         [u8; N]: Pixel,
     {
-        let mut rest = &mut *output;
+        Self::encode_range_with_options::<N>(
+            index,
+            px_prev,
+            run,
+            pixels,
+            output,
+            flush,
+            &EncodeOptions::DEFAULT,
+        )
+    }
+
+    /// Encode range of pixels into output slice with heuristics controlled by `options`.\
+    #[inline]
+    pub(crate) fn encode_range_with_options<const N: usize>(
+        index: &mut [[u8; 4]; 64],
+        px_prev: &mut [u8; N],
+        run: &mut usize,
+        pixels: &[u8],
+        output: &mut [u8],
+        flush: bool,
+        options: &EncodeOptions,
+    ) -> Result<usize, EncodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        // Run is flushed when it reaches `max_run` pixels.
+        let run_limit = options.max_run.clamp(1, 62) as usize - 1;
 
-        assert_eq!(pixels.len() % N, 0);
+        let mut rest = &mut *output;
 
         // let mut chunks = pixels.chunks_exact(N);
-        let mut pixels = bytemuck::cast_slice::<_, [u8; N]>(pixels);
+        let (mut pixels, _) = pixels.as_chunks::<N>();
+        let mut literal = false;
 
         loop {
             match pixels {
@@ -129,17 +423,29 @@ impl Qoi {
                     pixels = tail;
                     if likely(rest.len() > 7) {
                         if *px == *px_prev {
-                            if *run == 61 || unlikely(pixels.is_empty()) {
+                            if *run >= run_limit || unlikely(flush && pixels.is_empty()) {
                                 rest[0] = QOI_OP_RUN | (*run as u8);
                                 rest = &mut rest[1..];
                                 *run = 0;
                             } else {
                                 *run += 1;
+
+                                // Skip over the rest of the run at once.
+                                // Last pixel is left to flush the run.
+                                let avail = match flush {
+                                    true => pixels.len().saturating_sub(1),
+                                    false => pixels.len(),
+                                };
+                                let skip = run_length(&pixels[..avail], px, run_limit - *run);
+                                pixels = &pixels[skip.min(pixels.len())..];
+                                *run += skip;
                             }
                         } else {
+                            let was_literal = core::mem::replace(&mut literal, false);
+
                             match run {
                                 0 => {}
-                                1 => {
+                                1 if options.index_single_run => {
                                     // While not following reference encoder
                                     // this produces valid QOI and have the exactly same size.
                                     // Decoding is slightly faster.
@@ -147,7 +453,7 @@ impl Qoi {
                                     if unlikely(index_pos == 0x35 && index[0x35] == [0; 4]) {
                                         rest[0] = QOI_OP_RUN;
                                     } else {
-                                        rest[0] = QOI_OP_INDEX | index_pos as u8;
+                                        rest[0] = QOI_OP_INDEX | index_pos;
                                     }
                                     rest = &mut rest[1..];
                                     *run = 0;
@@ -161,14 +467,23 @@ impl Qoi {
 
                             match rest {
                                 [b1, b2, b3, b4, b5, ..] => {
+                                    // Hash is a single multiplication of packed channels.
+                                    // Updating previous hash from channel differences was measured slower
+                                    // for every kind of image except gradients without alpha channel,
+                                    // differences are needed before index lookup and hash becomes a loop-carried dependency.
                                     let index_pos = px.hash();
 
-                                    if index[index_pos as usize] == px.rgba() {
-                                        *b1 = QOI_OP_INDEX | index_pos as u8;
+                                    if options.index_lookup
+                                        && index[index_pos as usize] == px.rgba()
+                                    {
+                                        *b1 = QOI_OP_INDEX | index_pos;
                                         rest = &mut rest[1..];
                                     } else {
                                         index[index_pos as usize] = px.rgba();
 
+                                        // Branch is well predicted for opaque images.
+                                        // Separate alpha-free loop for opaque blocks was measured slower,
+                                        // block scan and second copy of the loop cost more than this check.
                                         if N == 4 && px_prev.a() != px.a() {
                                             cold();
                                             let [r, g, b, a] = px.rgba();
@@ -195,14 +510,30 @@ impl Qoi {
                                                 *b3 = g;
                                                 *b4 = b;
                                                 rest = &mut rest[4..];
+
+                                                // Stretch of literals is likely to continue after second literal in a row.
+                                                if was_literal {
+                                                    *px_prev = *px;
+                                                    let count = encode_literals(
+                                                        index, px_prev, pixels, rest,
+                                                    );
+                                                    pixels = &pixels[count.min(pixels.len())..];
+                                                    let rest_len = rest.len();
+                                                    rest = &mut core::mem::take(&mut rest)
+                                                        [(count * 4).min(rest_len)..];
+                                                    continue;
+                                                }
+                                                literal = true;
                                             }
                                         }
                                     }
                                     *px_prev = *px;
                                 }
                                 _ => {
+                                    // Never happens as `rest` had more than 7 bytes before pending run was written.
+                                    // Error is returned instead of panic to keep encoder panic-free.
                                     cold();
-                                    unreachable!()
+                                    return Err(EncodeError::OutputIsTooSmall);
                                 }
                             }
                         }
@@ -223,13 +554,134 @@ impl Qoi {
         Ok(output.len() - tail)
     }
 
+    /// Encode range of pixels into `sink` in chunks.\
+    /// Pending run is written at the end of the range only if `flush` is `true`.
+    pub(crate) fn encode_range_to_sink<const N: usize>(
+        index: &mut [[u8; 4]; 64],
+        px_prev: &mut [u8; N],
+        run: &mut usize,
+        pixels: &[u8],
+        flush: bool,
+        sink: &mut impl ByteSink,
+        options: &EncodeOptions,
+    ) -> Result<(), EncodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        const CHUNK: usize = 4096;
+
+        let mut chunks = pixels.chunks(CHUNK * N).peekable();
+
+        while let Some(chunk) = chunks.next() {
+            // Enough for any chunk to be encoded, see `Qoi::encoded_size_limit`.
+            let hint = chunk.len() / N * (N + 1) + QOI_PADDING;
+            let last = flush && chunks.peek().is_none();
+
+            let size = Self::encode_range_with_options::<N>(
+                index,
+                px_prev,
+                run,
+                chunk,
+                sink.buffer(hint),
+                last,
+                options,
+            )?;
+            sink.advance(size);
+        }
+
+        Ok(())
+    }
+
+    /// Encode all pixels of the image in small chunks followed by end marker into `sink`.\
+    /// `fill` is called to write next chunk of source pixels into temporary buffer on the stack.
+    /// Encoding stops at the first error returned by `fill`.
+    pub(crate) fn encode_chunks<const N: usize>(
+        &self,
+        sink: &mut impl ByteSink,
+        mut fill: impl FnMut(&mut [[u8; N]]) -> Result<(), EncodeError>,
+    ) -> Result<(), EncodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        const CHUNK: usize = 256;
+
+        let mut index = [[0; 4]; 64];
+        let mut px_prev = Pixel::new_opaque();
+        let mut run = 0;
+
+        let mut buffer = [[0; N]; CHUNK];
+        let mut left = self.pixels_len(1)?;
+
+        while left > 0 {
+            let chunk = &mut buffer[..left.min(CHUNK)];
+            fill(chunk)?;
+            left -= chunk.len();
+
+            Self::encode_range_to_sink::<N>(
+                &mut index,
+                &mut px_prev,
+                &mut run,
+                chunk.as_flattened(),
+                left == 0,
+                sink,
+                &EncodeOptions::DEFAULT,
+            )?;
+        }
+
+        Self::write_padding_to_sink(sink)
+    }
+
     /// Returns maximum size of the `Qoi::encode` output size.\
-    /// Using smaller slice may cause `Qoi::encode` to return `Err(EncodeError::OutputIsTooSmall)`.
+    /// Using smaller slice may cause `Qoi::encode` to return `Err(EncodeError::OutputIsTooSmall)`.\
+    /// Saturates at `usize::MAX` if the size does not fit into `usize`.
+    #[inline]
+    pub const fn encoded_size_limit(&self) -> usize {
+        (self.width as usize)
+            .saturating_mul(self.height as usize)
+            .saturating_mul(self.colors.has_alpha() as usize + 4)
+            .saturating_add(QOI_HEADER_SIZE + QOI_PADDING)
+    }
+
+    /// Returns maximum size of the `Qoi::encode` output for images where at most
+    /// `literal_percent` percent of pixels are encoded as literal RGB or RGBA ops.\
+    /// Every other pixel is encoded as run, index, diff or luma op taking at most 2 bytes,
+    /// so the bound is much tighter than [`Qoi::encoded_size_limit`] for typical content.\
+    /// `literal_percent` greater than `100` is treated as `100`, giving the same value as [`Qoi::encoded_size_limit`].
+    ///
+    /// Content is not checked against the declared density.
+    /// Encoding image exceeding it into slice of this size returns [`EncodeError::OutputIsTooSmall`].\
+    /// Saturates at `usize::MAX` if the size does not fit into `usize`.
     #[inline]
-    pub fn encoded_size_limit(&self) -> usize {
-        self.width as usize * self.height as usize * (self.colors.has_alpha() as usize + 4)
-            + QOI_HEADER_SIZE
-            + QOI_PADDING
+    pub fn encoded_size_limit_tight(&self, literal_percent: u8) -> usize {
+        let literal = literal_percent.min(100) as usize;
+        let literal_size = self.colors.has_alpha() as usize + 4;
+
+        let px_count = (self.width as usize).saturating_mul(self.height as usize);
+        let per_100 = literal * literal_size + (100 - literal) * 2;
+
+        match px_count.checked_mul(per_100) {
+            Some(size) => size
+                .div_ceil(100)
+                .saturating_add(QOI_HEADER_SIZE + QOI_PADDING),
+            None => usize::MAX,
+        }
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image.\
+    /// Encoded image is written into array on the stack, which suits tiny images like icons and cursors
+    /// without requiring `alloc`.\
+    /// `MAX` can be computed from constant image description with [`Qoi::encoded_size_limit`].
+    ///
+    /// On success this function returns `Ok((array, size))` with first `size` bytes of `array` containing encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_array<const MAX: usize>(
+        &self,
+        pixels: &[u8],
+    ) -> Result<([u8; MAX], usize), EncodeError> {
+        let mut output = [0; MAX];
+        let size = self.encode(pixels, &mut output)?;
+        Ok((output, size))
     }
 
     /// Encode raw RGB or RGBA pixels into a QOI image.\
@@ -240,15 +692,305 @@ impl Qoi {
     #[cfg(feature = "alloc")]
     #[inline]
     pub fn encode_alloc(&self, pixels: &[u8]) -> Result<Vec<u8>, EncodeError> {
-        let limit = self.encoded_size_limit();
-        let mut output = vec![0; limit];
+        let mut output = Vec::new();
+        self.encode_into_vec(pixels, &mut output)?;
+        Ok(output)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image.\
+    /// Encoded image replaces content of `output`, its capacity is reused,
+    /// so encoding frames of the same size repeatedly does not allocate after the first one.
+    ///
+    /// On success this function returns `Ok(())` with `output` containing encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    /// `output` is left empty.
+    #[cfg(feature = "alloc")]
+    pub fn encode_into_vec(&self, pixels: &[u8], output: &mut Vec<u8>) -> Result<(), EncodeError> {
+        output.clear();
+        let pixels = self.image_pixels(pixels, self.colors.channels())?;
+        output.reserve(self.encoded_size_limit());
+
+        let mut sink = VecSink::from_vec(core::mem::take(output));
+        let result = self
+            .write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)
+            .and_then(|()| self.encode_to_sink(pixels, &mut sink, &EncodeOptions::DEFAULT));
+
+        *output = sink.into_vec();
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                output.clear();
+                Err(err)
+            }
+        }
+    }
+
+    /// Encode many images of raw RGB or RGBA pixels back-to-back into `arena`.\
+    /// Images are appended after existing content of `arena`, its capacity is reused
+    /// and grows as needed, so encoding many small images does not allocate per image.
+    ///
+    /// On success this function returns `Ok(offsets)` with offset in `arena` where each image starts.
+    /// Image `i` ends where image `i + 1` starts, last image ends at the end of `arena`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    /// Images encoded before the failed one are left in `arena`.
+    #[cfg(feature = "alloc")]
+    pub fn encode_batch<'a>(
+        images: impl IntoIterator<Item = (&'a Qoi, &'a [u8])>,
+        arena: &mut Vec<u8>,
+    ) -> Result<Vec<usize>, EncodeError> {
+        let mut sink = VecSink::from_vec(core::mem::take(arena));
+        let mut offsets = Vec::new();
+        let mut result = Ok(());
+        let mut start = sink.written();
+
+        for (qoi, pixels) in images {
+            start = sink.written();
+
+            result = qoi
+                .write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)
+                .and_then(|()| qoi.encode_to_sink(pixels, &mut sink, &EncodeOptions::DEFAULT));
+
+            if result.is_err() {
+                break;
+            }
+
+            offsets.push(start);
+        }
+
+        *arena = sink.into_vec();
+
+        match result {
+            Ok(()) => Ok(offsets),
+            Err(err) => {
+                // Drop partially encoded image.
+                arena.truncate(start);
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns exact size of the QOI image encoded from raw RGB or RGBA pixels.\
+    /// Ops are chosen the same way [`Qoi::encode`] does, but only their sizes are summed
+    /// and nothing is written.\
+    /// Allows to pre-allocate arena space or pack multiple images into one buffer
+    /// before committing to an encode.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encoded_size(&self, pixels: &[u8]) -> Result<usize, EncodeError> {
+        let pixels = self.image_pixels(pixels, self.colors.channels())?;
+
+        let ops_size = match self.colors.has_alpha() {
+            true => Self::ops_size::<4>(pixels),
+            false => Self::ops_size::<3>(pixels),
+        };
+
+        Ok(QOI_HEADER_SIZE + ops_size + QOI_PADDING)
+    }
+
+    /// Returns size of ops encoding `pixels`.\
+    /// Mirrors op selection of [`Qoi::encode_range`].
+    fn ops_size<const N: usize>(pixels: &[u8]) -> usize
+    where
+        [u8; N]: Pixel,
+    {
+        let mut index = [[0; 4]; 64];
+        let mut px_prev = <[u8; N]>::new_opaque();
+        let mut run = 0;
+        let mut size = 0;
+
+        let (pixels, _) = pixels.as_chunks::<N>();
+        let last = pixels.len().wrapping_sub(1);
+
+        for (i, px) in pixels.iter().enumerate() {
+            if *px == px_prev {
+                if run == 61 || i == last {
+                    size += 1;
+                    run = 0;
+                } else {
+                    run += 1;
+                }
+                continue;
+            }
+
+            if run > 0 {
+                size += 1;
+                run = 0;
+            }
+
+            let index_pos = px.hash() as usize;
+            if index[index_pos] == px.rgba() {
+                size += 1;
+            } else {
+                index[index_pos] = px.rgba();
+
+                if N == 4 && px_prev.a() != px.a() {
+                    size += 5;
+                } else {
+                    let v = px.var(&px_prev);
+                    size += match (v.diff(), v.luma()) {
+                        (Some(_), _) => 1,
+                        (None, Some(_)) => 2,
+                        (None, None) => 4,
+                    };
+                }
+            }
+            px_prev = *px;
+        }
+
+        size
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image.\
+    /// Encoded image is written into allocated `Vec` of exact size.\
+    /// Size is found with [`Qoi::encoded_size`] pass first, so pixels are processed twice,
+    /// trading encoding speed for no over-allocation.
+    ///
+    /// On success this function returns `Ok(vec)` with `vec` containing encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    pub fn encode_alloc_exact(&self, pixels: &[u8]) -> Result<Vec<u8>, EncodeError> {
+        let size = self.encoded_size(pixels)?;
+
+        let mut output = vec![0; size];
         match self.encode(pixels, &mut output) {
-            Ok(size) => {
-                output.truncate(size);
-                Ok(output)
+            Ok(written) if written == size => Ok(output),
+            Ok(_) | Err(EncodeError::OutputIsTooSmall) => {
+                // Size pass disagrees with the encoder, fall back to worst-case sized buffer.
+                debug_assert!(false, "encoded_size does not match encoded image");
+                self.encode_alloc(pixels)
             }
-            Err(EncodeError::OutputIsTooSmall) => unreachable(),
             Err(err) => Err(err),
         }
     }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image choosing number of channels automatically.\
+    /// If color space has alpha channel but every pixel is fully opaque,
+    /// image is encoded without alpha channel, so it decodes into smaller RGB output.\
+    /// Encoded image is written into allocated `Vec`, its header describes chosen color space.
+    ///
+    /// On success this function returns `Ok(vec)` with `vec` containing encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    pub fn encode_alloc_auto(&self, pixels: &[u8]) -> Result<Vec<u8>, EncodeError> {
+        if !self.colors.has_alpha() {
+            return self.encode_alloc(pixels);
+        }
+
+        let pixels = self.image_pixels(pixels, 4)?;
+
+        if pixels.chunks_exact(4).any(|px| px[3] != 0xff) {
+            return self.encode_alloc(pixels);
+        }
+
+        let qoi = Qoi {
+            colors: self.colors.with_alpha(false),
+            ..*self
+        };
+
+        let mut sink = VecSink::with_capacity(qoi.encoded_size_limit());
+        let mut source = pixels.chunks_exact(4);
+
+        let result = qoi
+            .write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)
+            .and_then(|()| {
+                qoi.encode_chunks::<3>(&mut sink, |chunk| {
+                    for (px, src) in chunk.iter_mut().zip(source.by_ref()) {
+                        px.copy_from_slice(&src[..3]);
+                    }
+                    Ok(())
+                })
+            });
+
+        result.map(|()| sink.into_vec())
+    }
+}
+
+/// Encodes sequence of pixels that all require `QOI_OP_RGB` op.\
+/// Photos are mostly encoded as long stretches of such ops.
+/// Stretch is handled in a tight loop without run bookkeeping
+/// and ends on first pixel that can be encoded with any other op.
+///
+/// Returns number of encoded pixels.\
+/// Kept out of line to not bloat the main encoding loop.
+#[inline(never)]
+fn encode_literals<const N: usize>(
+    index: &mut [[u8; 4]; 64],
+    px_prev: &mut [u8; N],
+    pixels: &[[u8; N]],
+    output: &mut [u8],
+) -> usize
+where
+    [u8; N]: Pixel,
+{
+    let mut count = 0;
+    for (px, op) in pixels.iter().zip(output.as_chunks_mut::<4>().0) {
+        if N == 4 && px.a() != px_prev.a() {
+            break;
+        }
+
+        // Checks are combined without short-circuiting,
+        // noisy pixels fail all of them and branch mispredictions dominate otherwise.
+        let v = px.var(px_prev);
+        let index_pos = px.hash() as usize;
+
+        if (*px == *px_prev)
+            | (index[index_pos] == px.rgba())
+            | v.diff().is_some()
+            | v.luma().is_some()
+        {
+            break;
+        }
+
+        index[index_pos] = px.rgba();
+
+        let [r, g, b] = px.rgb();
+        *op = [QOI_OP_RGB, r, g, b];
+
+        *px_prev = *px;
+        count += 1;
+    }
+
+    count
+}
+
+/// Number of pixels compared at once by [`run_length`].
+const RUN_LANES: usize = 16;
+
+/// Returns number of leading pixels in `pixels` equal to `px`, up to `max`.\
+/// Pixels are compared in blocks of [`RUN_LANES`] that compile to wide vector compares,
+/// so long runs are skipped much faster than pixel by pixel.
+#[inline]
+fn run_length<const N: usize>(pixels: &[[u8; N]], px: &[u8; N], max: usize) -> usize {
+    let pixels = &pixels[..pixels.len().min(max)];
+    let splat = [*px; RUN_LANES];
+
+    let mut count = 0;
+    for block in pixels.chunks_exact(RUN_LANES) {
+        if *block != splat[..] {
+            break;
+        }
+        count += RUN_LANES;
+    }
+
+    count
+        + pixels[count.min(pixels.len())..]
+            .iter()
+            .take_while(|&next| next == px)
+            .count()
+}
+
+/// Fills `chunk` with next pixels from `pixels` dropping alpha channel if `N` is `3`.
+fn fill_from_iter<const N: usize>(
+    chunk: &mut [[u8; N]],
+    pixels: &mut impl Iterator<Item = [u8; 4]>,
+) -> Result<(), EncodeError> {
+    for px in chunk {
+        match pixels.next() {
+            None => return Err(EncodeError::NotEnoughPixelData),
+            Some(rgba) => px.copy_from_slice(&rgba[..N]),
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,71 @@
+use super::*;
+
+/// Stateful encoder that accepts pixels in multiple calls.\
+/// Holds color index, previous pixel and pending run between calls.
+///
+/// Header is not written, see [`Qoi::encode_header`].
+/// Call [`Encoder::finish`] after the last pixel to write pending run and the end marker.
+#[derive(Clone, Copy, Debug)]
+pub struct Encoder {
+    colors: Colors,
+    index: [[u8; 4]; 64],
+    px_prev: [u8; 4],
+    run: usize,
+}
+
+impl Encoder {
+    /// Returns new encoder for pixels in specified color space.
+    #[inline]
+    pub const fn new(colors: Colors) -> Self {
+        Encoder {
+            colors,
+            index: [[0; 4]; 64],
+            px_prev: [0, 0, 0, 255],
+            run: 0,
+        }
+    }
+
+    /// Encode raw RGB or RGBA pixels into `output` slice.\
+    /// `pixels` length must be a multiple of number of channels.\
+    /// Run of equal pixels at the end is kept pending and continued by the next call.\
+    /// Encoder is updated only on success, so failed call can be retried with larger `output`.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded data.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn push_pixels(&mut self, pixels: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+        match self.colors.has_alpha() {
+            true => self.push_pixels_impl::<4>(pixels, output),
+            false => self.push_pixels_impl::<3>(pixels, output),
+        }
+    }
+
+    fn push_pixels_impl<const N: usize>(
+        &mut self,
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let mut index = self.index;
+        let mut px_prev = [0; N];
+        px_prev.copy_from_slice(&self.px_prev[..N]);
+        let mut run = self.run;
+
+        let size =
+            Qoi::encode_range_impl::<N>(&mut index, &mut px_prev, &mut run, pixels, output, false)?;
+
+        self.index = index;
+        self.px_prev[..N].copy_from_slice(&px_prev);
+        self.run = run;
+        Ok(size)
+    }
+
+    /// Writes pending run and the end marker into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the written data.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn finish(mut self, output: &mut [u8]) -> Result<usize, EncodeError> {
+        Qoi::finish_range(&mut self.run, output)
+    }
+}
@@ -0,0 +1,575 @@
+//! Experimental color index parameters.
+//!
+//! Allows to measure how alternative index hash functions and smaller index tables
+//! affect compression.\
+//! Streams produced with anything but [`SpecHash`] and 64 entries table are not valid QOI
+//! and can be decoded only with [`decode_experiment`] using the same parameters.
+//!
+//! Functions here are straightforward reference-like implementations
+//! and are not tuned for speed. Standard encoder and decoder are not affected.
+//!
+//! # Paeth pre-filter
+//!
+//! Images with [`Capabilities::PAETH_FILTER`](crate::ext::Capabilities::PAETH_FILTER) flag in extended header
+//! have each pixel replaced with its wrapping difference from Paeth predictor
+//! of left, upper and upper-left neighbours before standard QOI encoding.
+//! Neighbours outside of the image are zeros.
+//! Vertical gradients that defeat QOI become long runs of equal differences.\
+//! Use [`ExtHeader::encode_image`](crate::ext::ExtHeader::encode_image)
+//! and [`ExtImage::decode`](crate::ext::ExtImage::decode) to write and read such images.
+//!
+//! # Packed color index
+//!
+//! [`ColorIndex`] stores the index as packed `u32` values instead of arrays of channels.
+//! [`encode_color_index`] and [`decode_color_index`] use it to produce and read standard QOI streams,
+//! so index layouts can be compared on different CPUs.
+
+use crate::bytes::{ByteSink, SliceSink};
+
+use super::*;
+
+/// Hash function to pick color index entry.
+pub trait IndexHash {
+    /// Name of the hash function for reports.
+    const NAME: &'static str;
+
+    /// Returns hash value of the pixel.\
+    /// Entry is selected by hash value modulo table size.
+    fn hash(px: [u8; 4]) -> u32;
+}
+
+/// Hash function defined by QOI specification.
+#[derive(Clone, Copy, Debug)]
+pub struct SpecHash;
+
+impl IndexHash for SpecHash {
+    const NAME: &'static str = "spec";
+
+    #[inline]
+    fn hash([r, g, b, a]: [u8; 4]) -> u32 {
+        r as u32 * 3 + g as u32 * 5 + b as u32 * 7 + a as u32 * 11
+    }
+}
+
+/// Multiplicative (Fibonacci) hash of the whole pixel value.
+#[derive(Clone, Copy, Debug)]
+pub struct FibonacciHash;
+
+impl IndexHash for FibonacciHash {
+    const NAME: &'static str = "fibonacci";
+
+    #[inline]
+    fn hash(px: [u8; 4]) -> u32 {
+        u32::from_le_bytes(px).wrapping_mul(0x9E37_79B9) >> 26
+    }
+}
+
+/// Hash that xors shifted channels.
+#[derive(Clone, Copy, Debug)]
+pub struct XorHash;
+
+impl IndexHash for XorHash {
+    const NAME: &'static str = "xor";
+
+    #[inline]
+    fn hash([r, g, b, a]: [u8; 4]) -> u32 {
+        (r ^ g.rotate_left(2) ^ b.rotate_left(4) ^ a.rotate_left(6)) as u32
+    }
+}
+
+#[inline]
+fn entry<H: IndexHash, const S: usize>(px: [u8; 4]) -> usize {
+    H::hash(px) as usize % S
+}
+
+struct TableSize<const S: usize>;
+
+impl<const S: usize> TableSize<S> {
+    const CHECK: () = assert!(
+        S.is_power_of_two() && S <= 64,
+        "Index table size must be power of two no greater than 64"
+    );
+}
+
+/// Fails to compile if `S` is not valid index table size.
+#[inline]
+fn check_table_size<const S: usize>() {
+    let () = TableSize::<S>::CHECK;
+}
+
+/// Encode raw RGB or RGBA pixels using specified index hash function `H` and index table size `S`.\
+/// `S` must be power of two no greater than `64`, otherwise compilation fails.
+///
+/// On success this function returns `Ok(size)` with `size` of the encoded image.\
+/// On failure this function returns `Err(err)` with `err` describing cause of the error.
+pub fn encode_experiment<H: IndexHash, const S: usize>(
+    qoi: &Qoi,
+    pixels: &[u8],
+    output: &mut [u8],
+) -> Result<usize, EncodeError> {
+    check_table_size::<S>();
+
+    let channels = qoi.colors.channels();
+    let pixels = qoi.image_pixels(pixels, channels)?;
+
+    match output.first_chunk_mut() {
+        None => return Err(EncodeError::OutputIsTooSmall),
+        Some(header) => qoi.write_header(QOI_MAGIC.to_be_bytes(), header),
+    }
+
+    let mut index = [[0u8; 4]; S];
+    let mut prev = [0, 0, 0, 255];
+    let mut run = 0u8;
+    let mut pos = QOI_HEADER_SIZE;
+
+    let mut push = |bytes: &[u8], pos: &mut usize| match output.get_mut(*pos..*pos + bytes.len()) {
+        None => Err(EncodeError::OutputIsTooSmall),
+        Some(out) => {
+            out.copy_from_slice(bytes);
+            *pos += bytes.len();
+            Ok(())
+        }
+    };
+
+    let count = pixels.len() / channels;
+    for (i, src) in pixels.chunks_exact(channels).enumerate() {
+        let mut px = [0, 0, 0, 255];
+        px[..channels].copy_from_slice(src);
+
+        if px == prev {
+            run += 1;
+            if run == 62 || i + 1 == count {
+                push(&[QOI_OP_RUN | (run - 1)], &mut pos)?;
+                run = 0;
+            }
+            continue;
+        }
+
+        if run > 0 {
+            push(&[QOI_OP_RUN | (run - 1)], &mut pos)?;
+            run = 0;
+        }
+
+        let e = entry::<H, S>(px);
+        if index[e] == px {
+            push(&[QOI_OP_INDEX | e as u8], &mut pos)?;
+        } else {
+            index[e] = px;
+
+            if px[3] != prev[3] {
+                push(&[QOI_OP_RGBA, px[0], px[1], px[2], px[3]], &mut pos)?;
+            } else {
+                let var = px.var(&prev);
+                if let Some(diff) = var.diff() {
+                    push(&[diff], &mut pos)?;
+                } else if let Some(luma) = var.luma() {
+                    push(&luma, &mut pos)?;
+                } else {
+                    push(&[QOI_OP_RGB, px[0], px[1], px[2]], &mut pos)?;
+                }
+            }
+        }
+
+        prev = px;
+    }
+
+    let mut padding = [0; QOI_PADDING];
+    padding[QOI_PADDING - 1] = 1;
+    push(&padding, &mut pos)?;
+
+    Ok(pos)
+}
+
+/// Decode image encoded by [`encode_experiment`] with the same `H` and `S` parameters.\
+/// `S` must be power of two no greater than `64`, otherwise compilation fails.\
+/// Decoded raw RGB or RGBA pixels are written into `output` slice.
+///
+/// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+/// On failure this function returns `Err(err)` with `err` describing cause of the error.
+pub fn decode_experiment<H: IndexHash, const S: usize>(
+    bytes: &[u8],
+    output: &mut [u8],
+) -> Result<Qoi, DecodeError> {
+    check_table_size::<S>();
+
+    let qoi = Qoi::decode_header(bytes)?;
+    let channels = qoi.colors.channels();
+
+    let size = match qoi.checked_decoded_size() {
+        None => return Err(DecodeError::TooLarge),
+        Some(size) => size,
+    };
+
+    let output = match output.get_mut(..size) {
+        None => return Err(DecodeError::OutputIsTooSmall),
+        Some(output) => output,
+    };
+
+    let mut index = [[0u8; 4]; S];
+    let mut px = [0, 0, 0, 255];
+    let mut run = 0;
+    let mut rest = &bytes[QOI_HEADER_SIZE..];
+
+    for out in output.chunks_exact_mut(channels) {
+        if run > 0 {
+            run -= 1;
+        } else {
+            match rest {
+                [QOI_OP_RGB, r, g, b, tail @ ..] => {
+                    px.set_rgb(*r, *g, *b);
+                    rest = tail;
+                }
+                [QOI_OP_RGBA, r, g, b, a, tail @ ..] => {
+                    px.set_rgba(*r, *g, *b, *a);
+                    rest = tail;
+                }
+                [b1 @ 0x00..=0x3f, tail @ ..] => {
+                    px = index[*b1 as usize % S];
+                    rest = tail;
+                }
+                [b1 @ 0x40..=0x7f, tail @ ..] => {
+                    px.add_rgb(
+                        ((b1 >> 4) & 0x03).wrapping_sub(2),
+                        ((b1 >> 2) & 0x03).wrapping_sub(2),
+                        (b1 & 0x03).wrapping_sub(2),
+                    );
+                    rest = tail;
+                }
+                [b1 @ 0x80..=0xbf, b2, tail @ ..] => {
+                    let vg = (b1 & 0x3f).wrapping_sub(32);
+                    px.add_rgb(
+                        ((b2 >> 4) & 0x0f).wrapping_sub(8).wrapping_add(vg),
+                        vg,
+                        (b2 & 0x0f).wrapping_sub(8).wrapping_add(vg),
+                    );
+                    rest = tail;
+                }
+                [b1 @ 0xc0..=0xfd, tail @ ..] => {
+                    run = b1 & 0x3f;
+                    rest = tail;
+                }
+                _ => return Err(DecodeError::NotEnoughData),
+            }
+
+            index[entry::<H, S>(px)] = px;
+        }
+
+        out.copy_from_slice(&px[..channels]);
+    }
+
+    Ok(qoi)
+}
+
+/// Color index of 64 entries stored as packed `u32` values, as QOI specification describes it.\
+/// Pixels are compared and inserted as single words instead of arrays of channels.
+///
+/// Used by [`encode_color_index`] and [`decode_color_index`]
+/// to compare index layouts, streams are standard QOI.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ColorIndex {
+    entries: [u32; 64],
+}
+
+impl Default for ColorIndex {
+    #[inline]
+    fn default() -> Self {
+        ColorIndex::new()
+    }
+}
+
+impl ColorIndex {
+    /// Returns index with all entries zeroed.
+    #[inline]
+    pub const fn new() -> Self {
+        ColorIndex { entries: [0; 64] }
+    }
+
+    /// Returns position of the pixel in the index.
+    #[inline]
+    pub fn position(px: [u8; 4]) -> u8 {
+        px.hash()
+    }
+
+    /// Returns pixel at position `pos`.\
+    /// Only lower 6 bits of `pos` are used.
+    #[inline]
+    pub fn get(&self, pos: u8) -> [u8; 4] {
+        self.entries[(pos & 63) as usize].to_ne_bytes()
+    }
+
+    /// Returns `true` if pixel at position `pos` is equal to `px`.\
+    /// Only lower 6 bits of `pos` are used.
+    #[inline]
+    pub fn matches(&self, pos: u8, px: [u8; 4]) -> bool {
+        self.entries[(pos & 63) as usize] == u32::from_ne_bytes(px)
+    }
+
+    /// Stores `px` at position `pos`.\
+    /// Only lower 6 bits of `pos` are used.
+    #[inline]
+    pub fn insert(&mut self, pos: u8, px: [u8; 4]) {
+        self.entries[(pos & 63) as usize] = u32::from_ne_bytes(px);
+    }
+}
+
+/// Encode raw RGB or RGBA pixels into a QOI image keeping color index in [`ColorIndex`].\
+/// Produces the same stream as [`Qoi::encode`].
+///
+/// On success this function returns `Ok(size)` with `size` of the encoded image.\
+/// On failure this function returns `Err(err)` with `err` describing cause of the error.
+pub fn encode_color_index(
+    qoi: &Qoi,
+    pixels: &[u8],
+    output: &mut [u8],
+) -> Result<usize, EncodeError> {
+    let channels = qoi.colors.channels();
+    let pixels = qoi.image_pixels(pixels, channels)?;
+
+    let mut sink = SliceSink::new(output);
+    qoi.write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)?;
+
+    let mut index = ColorIndex::new();
+    let mut prev = [0, 0, 0, 255];
+    let mut run = 0u8;
+
+    let count = pixels.len() / channels;
+    for (i, src) in pixels.chunks_exact(channels).enumerate() {
+        let mut px = [0, 0, 0, 255];
+        px[..channels].copy_from_slice(src);
+
+        if px == prev {
+            run += 1;
+            if run == 62 || i + 1 == count {
+                sink.write_bytes(&[QOI_OP_RUN | (run - 1)])?;
+                run = 0;
+            }
+            continue;
+        }
+
+        match run {
+            0 => {}
+            // Same as standard encoder, see `Qoi::encode_range`.
+            1 if !(prev.hash() == 0x35 && index.matches(0x35, [0; 4])) => {
+                sink.write_bytes(&[QOI_OP_INDEX | prev.hash()])?;
+                run = 0;
+            }
+            _ => {
+                sink.write_bytes(&[QOI_OP_RUN | (run - 1)])?;
+                run = 0;
+            }
+        }
+
+        let pos = ColorIndex::position(px);
+        if index.matches(pos, px) {
+            sink.write_bytes(&[QOI_OP_INDEX | pos])?;
+        } else {
+            index.insert(pos, px);
+
+            if px[3] != prev[3] {
+                sink.write_bytes(&[QOI_OP_RGBA, px[0], px[1], px[2], px[3]])?;
+            } else {
+                let var = px.var(&prev);
+                if let Some(diff) = var.diff() {
+                    sink.write_bytes(&[diff])?;
+                } else if let Some(luma) = var.luma() {
+                    sink.write_bytes(&luma)?;
+                } else {
+                    sink.write_bytes(&[QOI_OP_RGB, px[0], px[1], px[2]])?;
+                }
+            }
+        }
+
+        prev = px;
+    }
+
+    Qoi::write_padding_to_sink(&mut sink)?;
+    Ok(sink.written())
+}
+
+/// Decode a QOI image from bytes slice keeping color index in [`ColorIndex`].\
+/// Decoded raw RGB or RGBA pixels are written into `output` slice.
+///
+/// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+/// On failure this function returns `Err(err)` with `err` describing cause of the error.
+pub fn decode_color_index(bytes: &[u8], output: &mut [u8]) -> Result<Qoi, DecodeError> {
+    let qoi = Qoi::decode_header(bytes)?;
+    let channels = qoi.colors.channels();
+
+    let size = match qoi.checked_decoded_size() {
+        None => return Err(DecodeError::TooLarge),
+        Some(size) => size,
+    };
+
+    let output = match output.get_mut(..size) {
+        None => return Err(DecodeError::OutputIsTooSmall),
+        Some(output) => output,
+    };
+
+    let mut index = ColorIndex::new();
+    let mut px = [0, 0, 0, 255];
+    let mut run = 0;
+    let mut rest = &bytes[QOI_HEADER_SIZE..];
+
+    for out in output.chunks_exact_mut(channels) {
+        if run > 0 {
+            run -= 1;
+        } else {
+            match rest {
+                [QOI_OP_RGB, r, g, b, tail @ ..] => {
+                    px.set_rgb(*r, *g, *b);
+                    rest = tail;
+                }
+                [QOI_OP_RGBA, r, g, b, a, tail @ ..] => {
+                    px.set_rgba(*r, *g, *b, *a);
+                    rest = tail;
+                }
+                [b1 @ 0x00..=0x3f, tail @ ..] => {
+                    px = index.get(*b1);
+                    rest = tail;
+                }
+                [b1 @ 0x40..=0x7f, tail @ ..] => {
+                    px.add_rgb(
+                        ((b1 >> 4) & 0x03).wrapping_sub(2),
+                        ((b1 >> 2) & 0x03).wrapping_sub(2),
+                        (b1 & 0x03).wrapping_sub(2),
+                    );
+                    rest = tail;
+                }
+                [b1 @ 0x80..=0xbf, b2, tail @ ..] => {
+                    let vg = (b1 & 0x3f).wrapping_sub(32);
+                    px.add_rgb(
+                        ((b2 >> 4) & 0x0f).wrapping_sub(8).wrapping_add(vg),
+                        vg,
+                        (b2 & 0x0f).wrapping_sub(8).wrapping_add(vg),
+                    );
+                    rest = tail;
+                }
+                [b1 @ 0xc0..=0xfd, tail @ ..] => {
+                    run = b1 & 0x3f;
+                    rest = tail;
+                }
+                _ => return Err(DecodeError::NotEnoughData),
+            }
+
+            index.insert(ColorIndex::position(px), px);
+        }
+
+        out.copy_from_slice(&px[..channels]);
+    }
+
+    Ok(qoi)
+}
+
+#[inline]
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Returns Paeth prediction for pixel `i` from pixels preceding it.
+#[inline]
+fn predict<const N: usize>(pixels: &[[u8; N]], width: usize, i: usize) -> [u8; N] {
+    let x = i % width;
+    let zero = [0; N];
+
+    let left = if x > 0 { &pixels[i - 1] } else { &zero };
+    let up = if i >= width {
+        &pixels[i - width]
+    } else {
+        &zero
+    };
+    let up_left = if x > 0 && i >= width {
+        &pixels[i - width - 1]
+    } else {
+        &zero
+    };
+
+    let mut px = [0; N];
+    for c in 0..N {
+        px[c] = paeth(left[c], up[c], up_left[c]);
+    }
+    px
+}
+
+/// Encode raw RGB or RGBA pixels filtered with Paeth predictor without header.
+pub(crate) fn encode_paeth(
+    qoi: &Qoi,
+    pixels: &[u8],
+    output: &mut [u8],
+) -> Result<usize, EncodeError> {
+    let pixels = qoi.image_pixels(pixels, qoi.colors.channels())?;
+
+    let mut sink = SliceSink::new(output);
+    match qoi.colors.has_alpha() {
+        true => encode_paeth_impl::<4>(qoi, pixels.as_chunks().0, &mut sink)?,
+        false => encode_paeth_impl::<3>(qoi, pixels.as_chunks().0, &mut sink)?,
+    }
+    Ok(sink.written())
+}
+
+fn encode_paeth_impl<const N: usize>(
+    qoi: &Qoi,
+    pixels: &[[u8; N]],
+    sink: &mut SliceSink,
+) -> Result<(), EncodeError>
+where
+    [u8; N]: Pixel,
+{
+    let width = qoi.width as usize;
+    let mut pos = 0;
+
+    qoi.encode_chunks::<N>(sink, |chunk| {
+        for px in chunk {
+            let pred = predict(pixels, width, pos);
+            for c in 0..N {
+                px[c] = pixels[pos][c].wrapping_sub(pred[c]);
+            }
+            pos += 1;
+        }
+        Ok(())
+    })
+}
+
+/// Restores decoded pixels filtered with Paeth predictor in place.
+pub(crate) fn unfilter_paeth(qoi: &Qoi, output: &mut [u8]) -> Result<(), DecodeError> {
+    let size = match qoi.checked_decoded_size() {
+        None => return Err(DecodeError::TooLarge),
+        Some(size) => size,
+    };
+
+    let output = match output.get_mut(..size) {
+        None => return Err(DecodeError::OutputIsTooSmall),
+        Some(output) => output,
+    };
+
+    match qoi.colors.has_alpha() {
+        true => unfilter_paeth_impl::<4>(qoi, output),
+        false => unfilter_paeth_impl::<3>(qoi, output),
+    }
+    Ok(())
+}
+
+fn unfilter_paeth_impl<const N: usize>(qoi: &Qoi, output: &mut [u8])
+where
+    [u8; N]: Pixel,
+{
+    let width = qoi.width as usize;
+    let (pixels, _) = output.as_chunks_mut::<N>();
+
+    for i in 0..pixels.len() {
+        let pred = predict(pixels, width, i);
+        for c in 0..N {
+            pixels[i][c] = pixels[i][c].wrapping_add(pred[c]);
+        }
+    }
+}
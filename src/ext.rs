@@ -0,0 +1,272 @@
+//! Extended header for non-standard QOI extensions.
+//!
+//! Extended header starts with `b"qoix"` magic followed by the standard QOI header fields,
+//! version byte and capability flags.
+//! ```rust
+//! #[repr(C)]
+//! struct QoixHeader {
+//!     magic: [u8; 4],    // magic bytes "qoix"
+//!     width: u32,        // image width in pixels (BE)
+//!     height: u32,       // image height in pixels (BE)
+//!     channels: u8,      // 3 = RGB, 4 = RGBA
+//!     colorspace: u8,    // 0 = sRGB with linear alpha, 1 = all channels linear
+//!     version: u8,       // extended header version
+//!     capabilities: u32, // capability flags (BE)
+//! }
+//! ```
+//! Images with extended header are not valid QOI images and cannot be read by standard decoders.\
+//! Decoders must reject images with unknown version or capabilities.
+
+use core::{
+    convert::TryInto,
+    ops::{BitOr, BitOrAssign},
+};
+
+use super::*;
+
+/// Magic value of the extended header.
+pub const QOIX_MAGIC: [u8; 4] = *b"qoix";
+
+/// Latest supported version of the extended header.
+pub const QOIX_VERSION: u8 = 1;
+
+/// Size of the extended header in bytes.
+pub const QOIX_HEADER_SIZE: usize = QOI_HEADER_SIZE + 5;
+
+/// Set of capability flags stored in extended header.\
+/// Each flag denotes non-standard extension used by the image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    /// No extensions.
+    pub const NONE: Self = Capabilities(0);
+
+    /// Metadata sidecar is embedded after the header.\
+    /// It is prefixed with its length as `u32` (BE).
+    /// See [`meta`] module.
+    pub const METADATA: Self = Capabilities(1);
+
+    /// Pixels are replaced with difference from Paeth predictor before encoding.\
+    /// Predictor uses left, upper and upper-left neighbours, see [`experiment`] module.
+    #[cfg(feature = "experimental")]
+    pub const PAETH_FILTER: Self = Capabilities(2);
+
+    /// All capabilities supported by this version of the crate.
+    #[cfg(not(feature = "experimental"))]
+    pub const SUPPORTED: Self = Capabilities::METADATA;
+
+    /// All capabilities supported by this version of the crate.
+    #[cfg(feature = "experimental")]
+    pub const SUPPORTED: Self =
+        Capabilities(Capabilities::METADATA.0 | Capabilities::PAETH_FILTER.0);
+
+    /// Returns raw bits of the capability flags.
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns capability flags from raw bits.\
+    /// Returns `None` if unsupported bits are set.
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Option<Self> {
+        match bits & !Self::SUPPORTED.0 {
+            0 => Some(Capabilities(bits)),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if all flags from `other` are set in `self`.
+    #[inline]
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Capabilities(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Capabilities {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Extended header value.
+#[derive(Clone, Copy, Debug)]
+pub struct ExtHeader {
+    /// Standard QOI descriptor.
+    pub qoi: Qoi,
+
+    /// Version of the extended header.
+    pub version: u8,
+
+    /// Extensions used by the image.
+    pub capabilities: Capabilities,
+}
+
+impl ExtHeader {
+    /// Returns extended header of the latest version.
+    #[inline]
+    pub const fn new(qoi: Qoi, capabilities: Capabilities) -> Self {
+        ExtHeader {
+            qoi,
+            version: QOIX_VERSION,
+            capabilities,
+        }
+    }
+
+    /// Reads extended header from encoded image.\
+    /// Image data follows the header at offset [`QOIX_HEADER_SIZE`].
+    ///
+    /// Fails with [`DecodeError::UnsupportedVersion`] if header version is unknown
+    /// and with [`DecodeError::UnsupportedCapabilities`] if any unknown capability flag is set.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if bytes.len() < QOIX_HEADER_SIZE {
+            return Err(DecodeError::NotEnoughData);
+        }
+
+        let qoi = Qoi::decode_header_with_magic(bytes, QOIX_MAGIC)?;
+
+        let version = bytes[QOI_HEADER_SIZE];
+        if version == 0 || version > QOIX_VERSION {
+            return Err(DecodeError::UnsupportedVersion);
+        }
+
+        let mut bits = [0; 4];
+        bits.copy_from_slice(&bytes[QOI_HEADER_SIZE + 1..QOIX_HEADER_SIZE]);
+
+        let capabilities = match Capabilities::from_bits(u32::from_be_bytes(bits)) {
+            None => return Err(DecodeError::UnsupportedCapabilities),
+            Some(capabilities) => capabilities,
+        };
+
+        Ok(ExtHeader {
+            qoi,
+            version,
+            capabilities,
+        })
+    }
+
+    /// Writes extended header into `output`.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode(&self, output: &mut [u8]) -> Result<usize, EncodeError> {
+        if output.len() < QOIX_HEADER_SIZE {
+            return Err(EncodeError::OutputIsTooSmall);
+        }
+
+        let mut header = [0; QOI_HEADER_SIZE];
+        self.qoi.write_header(QOIX_MAGIC, &mut header);
+        output[..QOI_HEADER_SIZE].copy_from_slice(&header);
+        output[QOI_HEADER_SIZE] = self.version;
+        output[QOI_HEADER_SIZE + 1..QOIX_HEADER_SIZE]
+            .copy_from_slice(&self.capabilities.bits().to_be_bytes());
+
+        Ok(QOIX_HEADER_SIZE)
+    }
+}
+
+/// Image with extended header split into parts.
+#[derive(Clone, Copy, Debug)]
+pub struct ExtImage<'a> {
+    /// Extended header of the image.
+    pub header: ExtHeader,
+
+    /// Embedded metadata sidecar.\
+    /// Empty if image has no [`Capabilities::METADATA`] flag.
+    pub metadata: &'a [u8],
+
+    /// Encoded image data that follows header and metadata.
+    pub data: &'a [u8],
+}
+
+impl<'a> ExtImage<'a> {
+    /// Splits image with extended header into header, embedded metadata and image data.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        let header = ExtHeader::decode(bytes)?;
+        let mut rest = &bytes[QOIX_HEADER_SIZE..];
+
+        let mut metadata: &[u8] = &[];
+        if header.capabilities.contains(Capabilities::METADATA) {
+            if rest.len() < 4 {
+                return Err(DecodeError::NotEnoughData);
+            }
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            metadata = match rest[4..].get(..len) {
+                None => return Err(DecodeError::NotEnoughData),
+                Some(metadata) => metadata,
+            };
+            rest = &rest[4 + len..];
+        }
+
+        Ok(ExtImage {
+            header,
+            metadata,
+            data: rest,
+        })
+    }
+
+    /// Decode image data into `output` slice.\
+    /// See [`Qoi::decode_skip_header`].
+    #[inline]
+    pub fn decode(&self, output: &mut [u8]) -> Result<(), DecodeError> {
+        self.header.qoi.decode_skip_header(self.data, output)?;
+
+        #[cfg(feature = "experimental")]
+        if self
+            .header
+            .capabilities
+            .contains(Capabilities::PAETH_FILTER)
+        {
+            experiment::unfilter_paeth(&self.header.qoi, output)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ExtHeader {
+    /// Encode raw RGB or RGBA pixels into image with extended header and embedded metadata sidecar.\
+    /// Metadata is written only if header has [`Capabilities::METADATA`] flag.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_image(
+        &self,
+        metadata: &[u8],
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let mut size = self.encode(output)?;
+
+        if self.capabilities.contains(Capabilities::METADATA) {
+            let end = size + 4 + metadata.len();
+            if output.len() < end || metadata.len() > u32::MAX as usize {
+                return Err(EncodeError::OutputIsTooSmall);
+            }
+
+            output[size..size + 4].copy_from_slice(&(metadata.len() as u32).to_be_bytes());
+            output[size + 4..end].copy_from_slice(metadata);
+            size = end;
+        }
+
+        #[cfg(feature = "experimental")]
+        if self.capabilities.contains(Capabilities::PAETH_FILTER) {
+            let data = experiment::encode_paeth(&self.qoi, pixels, &mut output[size..])?;
+            return Ok(size + data);
+        }
+
+        let data = self.qoi.encode_skip_header(pixels, &mut output[size..])?;
+        Ok(size + data)
+    }
+}
@@ -0,0 +1,54 @@
+use std::io;
+
+use futures_util::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::*;
+
+use crate::io::{invalid_data, invalid_input, ChunkDecoder, ChunkEncoder, READ_BUFFER_SIZE};
+
+impl Qoi {
+    /// Encode raw RGB or RGBA pixels into a QOI image and write it into `writer` asynchronously.\
+    /// Pixels are encoded in small chunks, each written as soon as it is encoded.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] wrapping [`EncodeError::NotEnoughPixelData`]
+    /// if `pixels` is too small for the image.
+    pub async fn encode_async<W>(&self, pixels: &[u8], writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut encoder = ChunkEncoder::new(self, pixels).map_err(invalid_input)?;
+        while let Some(bytes) = encoder.next().map_err(invalid_input)? {
+            writer.write_all(bytes).await?;
+        }
+
+        writer.flush().await
+    }
+
+    /// Read and decode a QOI image from `reader` asynchronously.\
+    /// Decoded raw RGB or RGBA pixels are written into allocated `Vec`,
+    /// which grows as encoded data is read.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] wrapping [`DecodeError`] if image is invalid
+    /// and with [`io::ErrorKind::UnexpectedEof`] if reader ends before the last pixel.
+    pub async fn decode_async<R>(reader: &mut R) -> io::Result<(Self, Vec<u8>)>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut header = [0; QOI_HEADER_SIZE];
+        reader.read_exact(&mut header).await?;
+
+        let mut decoder = ChunkDecoder::new(&header).map_err(invalid_data)?;
+
+        let mut buffer = vec![0; READ_BUFFER_SIZE];
+        while !decoder.is_done() {
+            let len = reader.read(&mut buffer).await?;
+            if len == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            decoder.feed(&buffer[..len]).map_err(invalid_data)?;
+        }
+
+        Ok(decoder.finish())
+    }
+}
@@ -0,0 +1,295 @@
+//! Integration with [`image`](::image) crate.
+
+use std::{
+    convert::TryFrom,
+    io::{self, Read, Write},
+};
+
+use ::image::{
+    error::{
+        DecodingError, EncodingError, ImageFormatHint, LimitError, LimitErrorKind,
+        UnsupportedError, UnsupportedErrorKind,
+    },
+    ColorType, DynamicImage, ImageDecoder, ImageEncoder, ImageError, ImageFormat, ImageResult,
+    RgbImage, RgbaImage,
+};
+
+use alloc::{vec, vec::Vec};
+
+use super::*;
+
+/// Extension trait to encode [`DynamicImage`] into QOI.
+pub trait DynamicImageQoiExt {
+    /// Encode image into QOI.\
+    /// Images with alpha channel are encoded as SRGB with linear alpha,
+    /// images without alpha channel are encoded as SRGB.\
+    /// Luma images are expanded to RGB(A) and samples wider than 8 bits are converted to 8 bits.
+    ///
+    /// On success this function returns `Ok(bytes)` with encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    fn to_qoi(&self) -> Result<Vec<u8>, EncodeError>;
+}
+
+impl DynamicImageQoiExt for DynamicImage {
+    fn to_qoi(&self) -> Result<Vec<u8>, EncodeError> {
+        match self.color().has_alpha() {
+            true => {
+                let converted;
+                let rgba = match self.as_rgba8() {
+                    Some(rgba) => rgba,
+                    None => {
+                        converted = self.to_rgba8();
+                        &converted
+                    }
+                };
+
+                Qoi {
+                    width: rgba.width(),
+                    height: rgba.height(),
+                    colors: Colors::SrgbLinA,
+                }
+                .encode_alloc(rgba.as_raw())
+            }
+            false => {
+                let converted;
+                let rgb = match self.as_rgb8() {
+                    Some(rgb) => rgb,
+                    None => {
+                        converted = self.to_rgb8();
+                        &converted
+                    }
+                };
+
+                Qoi {
+                    width: rgb.width(),
+                    height: rgb.height(),
+                    colors: Colors::Srgb,
+                }
+                .encode_alloc(rgb.as_raw())
+            }
+        }
+    }
+}
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice into [`DynamicImage`].\
+    /// Images with alpha channel are decoded into [`DynamicImage::ImageRgba8`],
+    /// images without alpha channel are decoded into [`DynamicImage::ImageRgb8`].
+    ///
+    /// On success this function returns `Ok(image)` with decoded `image`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_image(bytes: &[u8]) -> Result<DynamicImage, DecodeError> {
+        let (qoi, pixels) = Self::decode_alloc(bytes)?;
+
+        let image = match qoi.colors.has_alpha() {
+            true => {
+                RgbaImage::from_raw(qoi.width, qoi.height, pixels).map(DynamicImage::ImageRgba8)
+            }
+            false => RgbImage::from_raw(qoi.width, qoi.height, pixels).map(DynamicImage::ImageRgb8),
+        };
+
+        // Decoded size fits into `usize`, so buffer always matches the dimensions.
+        image.ok_or(DecodeError::TooLarge)
+    }
+
+    /// Encode [`DynamicImage`] into QOI.\
+    /// Channels and color space are chosen from the image color type,
+    /// see [`DynamicImageQoiExt::to_qoi`].
+    #[inline]
+    pub fn encode_dynamic(image: &DynamicImage) -> Result<Vec<u8>, EncodeError> {
+        image.to_qoi()
+    }
+}
+
+/// Decoder of QOI images implementing [`ImageDecoder`].\
+/// Allows opening QOI images with [`DynamicImage::from_decoder`]
+/// and everything else built on top of [`ImageDecoder`].
+///
+/// Pixels are decoded from reader incrementally with [`QoiReadDecoder`].
+#[derive(Debug)]
+pub struct QoiDecoder<R> {
+    decoder: QoiReadDecoder<R>,
+}
+
+impl<R> QoiDecoder<R>
+where
+    R: Read,
+{
+    /// Returns new decoder reading image from `reader`.\
+    /// Reads and validates header.
+    pub fn new(reader: R) -> ImageResult<Self> {
+        let decoder = QoiReadDecoder::new(reader).map_err(image_error)?;
+        Ok(QoiDecoder { decoder })
+    }
+
+    /// Returns header of the image.
+    #[inline]
+    pub fn qoi(&self) -> Qoi {
+        self.decoder.qoi()
+    }
+}
+
+impl<'a, R> ImageDecoder<'a> for QoiDecoder<R>
+where
+    R: Read + 'a,
+{
+    type Reader = io::Cursor<Vec<u8>>;
+
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        let qoi = self.decoder.qoi();
+        (qoi.width, qoi.height)
+    }
+
+    #[inline]
+    fn color_type(&self) -> ColorType {
+        match self.decoder.qoi().colors.has_alpha() {
+            true => ColorType::Rgba8,
+            false => ColorType::Rgb8,
+        }
+    }
+
+    fn into_reader(self) -> ImageResult<Self::Reader> {
+        let len = match usize::try_from(self.total_bytes()) {
+            Err(_) => {
+                return Err(ImageError::Limits(LimitError::from_kind(
+                    LimitErrorKind::InsufficientMemory,
+                )))
+            }
+            Ok(len) => len,
+        };
+
+        let mut pixels = vec![0; len];
+        self.read_image(&mut pixels)?;
+        Ok(io::Cursor::new(pixels))
+    }
+
+    fn read_image(mut self, buf: &mut [u8]) -> ImageResult<()> {
+        assert_eq!(buf.len() as u64, self.total_bytes());
+
+        let channels = self.decoder.qoi().colors.channels();
+        let mut buf = buf;
+
+        while !buf.is_empty() {
+            let count = self.decoder.read_pixels(buf).map_err(image_error)?;
+            if count == 0 {
+                break;
+            }
+            buf = &mut buf[count * channels..];
+        }
+
+        Ok(())
+    }
+}
+
+/// Encoder of QOI images implementing [`ImageEncoder`].\
+/// Allows writing images with [`DynamicImage::write_with_encoder`]
+/// and everything else built on top of [`ImageEncoder`].
+///
+/// Accepts [`ColorType::Rgb8`], [`ColorType::Rgba8`] and [`ColorType::L8`] pixels.\
+/// Images with alpha channel are encoded as SRGB with linear alpha,
+/// images without alpha channel are encoded as SRGB.
+#[derive(Debug)]
+pub struct QoiEncoder<W> {
+    writer: W,
+}
+
+impl<W> QoiEncoder<W>
+where
+    W: Write,
+{
+    /// Returns new encoder writing image into `writer`.
+    #[inline]
+    pub fn new(writer: W) -> Self {
+        QoiEncoder { writer }
+    }
+
+    /// Returns underlying writer.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W> ImageEncoder for QoiEncoder<W>
+where
+    W: Write,
+{
+    fn write_image(
+        self,
+        buf: &[u8],
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+    ) -> ImageResult<()> {
+        let colors = match color_type {
+            ColorType::Rgb8 | ColorType::L8 => Colors::Srgb,
+            ColorType::Rgba8 => Colors::SrgbLinA,
+            _ => {
+                return Err(ImageError::Unsupported(
+                    UnsupportedError::from_format_and_kind(
+                        ImageFormatHint::Exact(ImageFormat::Qoi),
+                        UnsupportedErrorKind::Color(color_type.into()),
+                    ),
+                ))
+            }
+        };
+
+        assert_eq!(
+            buf.len() as u64,
+            width as u64 * height as u64 * color_type.bytes_per_pixel() as u64
+        );
+
+        let qoi = Qoi {
+            width,
+            height,
+            colors,
+        };
+
+        // Pixels are encoded straight into the writer,
+        // without allocating worst-case sized output buffer.
+        let mut encoder = QoiWriteEncoder::new(qoi, self.writer).map_err(encode_image_error)?;
+
+        match color_type {
+            ColorType::L8 => {
+                let mut rgb = [0; 3 * 1024];
+                for luma in buf.chunks(1024) {
+                    for (px, &l) in rgb.chunks_exact_mut(3).zip(luma) {
+                        px.fill(l);
+                    }
+                    encoder
+                        .write_pixels(&rgb[..luma.len() * 3])
+                        .map_err(encode_image_error)?;
+                }
+            }
+            _ => encoder.write_pixels(buf).map_err(encode_image_error)?,
+        }
+
+        encoder.finish().map_err(encode_image_error)?;
+        Ok(())
+    }
+}
+
+/// Converts I/O error of QOI encoding into [`ImageError`].\
+/// Invalid input is reported as encoding error.
+fn encode_image_error(err: io::Error) -> ImageError {
+    match err.kind() {
+        io::ErrorKind::InvalidInput => ImageError::Encoding(EncodingError::new(
+            ImageFormatHint::Exact(ImageFormat::Qoi),
+            err,
+        )),
+        _ => ImageError::IoError(err),
+    }
+}
+
+/// Converts I/O error of QOI decoding into [`ImageError`].\
+/// Invalid data is reported as decoding error.
+fn image_error(err: io::Error) -> ImageError {
+    match err.kind() {
+        io::ErrorKind::InvalidData => ImageError::Decoding(DecodingError::new(
+            ImageFormatHint::Exact(ImageFormat::Qoi),
+            err,
+        )),
+        _ => ImageError::IoError(err),
+    }
+}
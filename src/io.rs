@@ -0,0 +1,444 @@
+use std::{
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use super::*;
+
+/// Number of pixels encoded at once.
+pub(crate) const CHUNK: usize = 4096;
+
+/// Returns size of the buffer that fits any encoded chunk of [`CHUNK`] pixels.\
+/// Pending run of the previous chunk may be flushed before the first pixel,
+/// followed by at most `channels + 1` bytes per pixel and the end marker after the last chunk.
+#[inline]
+pub(crate) const fn chunk_buffer_size(channels: usize) -> usize {
+    1 + CHUNK * (channels + 1) + QOI_PADDING
+}
+
+/// Encodes pixels of the image chunk by chunk into reused buffer.\
+/// Shared by async encoders, which differ only in how encoded bytes are written.
+#[cfg(any(feature = "futures", feature = "tokio"))]
+pub(crate) struct ChunkEncoder<'a> {
+    qoi: Qoi,
+    chunks: core::slice::Chunks<'a, u8>,
+    state: EncState,
+    buffer: Vec<u8>,
+    header: bool,
+}
+
+#[cfg(any(feature = "futures", feature = "tokio"))]
+impl<'a> ChunkEncoder<'a> {
+    /// Returns encoder for `pixels` of the image described by `qoi`.\
+    /// Fails with [`EncodeError::NotEnoughPixelData`] if `pixels` is too small for the image.
+    pub fn new(qoi: &Qoi, pixels: &'a [u8]) -> Result<Self, EncodeError> {
+        let channels = qoi.colors.channels();
+        let pixels = qoi.image_pixels(pixels, channels)?;
+
+        Ok(ChunkEncoder {
+            qoi: *qoi,
+            chunks: pixels.chunks(CHUNK * channels),
+            state: EncState::new(),
+            buffer: vec![0; chunk_buffer_size(channels)],
+            header: true,
+        })
+    }
+
+    /// Returns next piece of encoded image, starting with the header.\
+    /// Returns `Ok(None)` when the whole image is encoded.
+    pub fn next(&mut self) -> Result<Option<&[u8]>, EncodeError> {
+        let size = match core::mem::take(&mut self.header) {
+            true => {
+                let mut size = self.qoi.encode_header(&mut self.buffer)?;
+                if self.chunks.len() == 0 {
+                    size += Qoi::finish_range(&mut 0, &mut self.buffer[size..])?;
+                }
+                size
+            }
+            false => match self.chunks.next() {
+                None => return Ok(None),
+                Some(chunk) => {
+                    self.qoi
+                        .encode_with_state(&mut self.state, chunk, &mut self.buffer)?
+                }
+            },
+        };
+
+        Ok(Some(&self.buffer[..size]))
+    }
+}
+
+/// Size of the buffer for reading encoded data by async decoders.
+#[cfg(any(feature = "futures", feature = "tokio"))]
+pub(crate) const READ_BUFFER_SIZE: usize = 16384;
+
+/// Maximum number of pixels single byte of encoded data can produce.
+#[cfg(any(feature = "futures", feature = "tokio"))]
+pub(crate) const MAX_PIXELS_PER_BYTE: usize = 62;
+
+/// Decodes image from fragments of encoded data into `Vec` of pixels.\
+/// Shared by async decoders, which differ only in how encoded bytes are read.
+///
+/// Output grows only by as many pixels as received data can produce,
+/// so header claiming huge image does not allocate before its data arrives.
+#[cfg(any(feature = "futures", feature = "tokio"))]
+pub(crate) struct ChunkDecoder {
+    qoi: Qoi,
+    decoder: Decoder,
+    output: Vec<u8>,
+    size: usize,
+}
+
+#[cfg(any(feature = "futures", feature = "tokio"))]
+impl ChunkDecoder {
+    /// Returns decoder for the image with `header`.\
+    /// Fails with [`DecodeError::TooLarge`] if decoded size does not fit into `usize`.
+    pub fn new(header: &[u8; QOI_HEADER_SIZE]) -> Result<Self, DecodeError> {
+        let qoi = Qoi::decode_header(header)?;
+
+        let size = match qoi.checked_decoded_size() {
+            None => return Err(DecodeError::TooLarge),
+            Some(size) => size,
+        };
+
+        Ok(ChunkDecoder {
+            qoi,
+            decoder: Decoder::with_header(qoi),
+            output: Vec::new(),
+            size,
+        })
+    }
+
+    /// Returns `true` if all pixels of the image are decoded.
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.decoder.is_done()
+    }
+
+    /// Decode next fragment of encoded image.
+    pub fn feed(&mut self, mut bytes: &[u8]) -> Result<(), DecodeError> {
+        let channels = self.qoi.colors.channels();
+
+        while !bytes.is_empty() && !self.decoder.is_done() {
+            // Pending run adds at most one more op worth of pixels.
+            let grow = (bytes.len() + 1)
+                .saturating_mul(MAX_PIXELS_PER_BYTE * channels)
+                .min(self.size - self.output.len());
+
+            let pos = self.output.len();
+            self.output.resize(pos + grow, 0);
+
+            let progress = self.decoder.feed(bytes, &mut self.output[pos..]);
+            let progress = match progress {
+                Ok(progress) => progress,
+                Err(err) => {
+                    self.output.truncate(pos);
+                    return Err(err);
+                }
+            };
+
+            self.output.truncate(pos + progress.pixels * channels);
+            bytes = &bytes[progress.bytes..];
+        }
+
+        Ok(())
+    }
+
+    /// Returns header and decoded pixels of the image.
+    #[inline]
+    pub fn finish(self) -> (Qoi, Vec<u8>) {
+        (self.qoi, self.output)
+    }
+}
+
+/// Encoder that writes QOI image into [`Write`] incrementally.\
+/// Header is written on creation, pixels are encoded as they are written
+/// and the end marker is written by [`QoiWriteEncoder::finish`].
+///
+/// Pixels may be written in chunks of arbitrary size, chunks don't have to be aligned to pixels.
+#[derive(Debug)]
+pub struct QoiWriteEncoder<W> {
+    writer: W,
+    qoi: Qoi,
+    state: EncState,
+    partial: [u8; 4],
+    partial_len: usize,
+    buffer: Vec<u8>,
+}
+
+impl<W> QoiWriteEncoder<W>
+where
+    W: Write,
+{
+    /// Returns new encoder for the image described by `qoi`.\
+    /// Writes header into `writer`.
+    pub fn new(qoi: Qoi, mut writer: W) -> io::Result<Self> {
+        let mut header = [0; QOI_HEADER_SIZE];
+        qoi.write_header(QOI_MAGIC.to_be_bytes(), &mut header);
+        writer.write_all(&header)?;
+
+        let channels = qoi.colors.channels();
+
+        Ok(QoiWriteEncoder {
+            writer,
+            qoi,
+            state: EncState::new(),
+            partial: [0; 4],
+            partial_len: 0,
+            buffer: vec![0; chunk_buffer_size(channels)],
+        })
+    }
+
+    /// Returns number of pixels encoded so far.
+    #[inline]
+    pub fn pixels(&self) -> u64 {
+        self.state.pixels()
+    }
+
+    /// Encode raw RGB or RGBA pixels and write them into underlying writer.\
+    /// Fails with [`io::ErrorKind::InvalidInput`] if more pixels than the image has are written.
+    pub fn write_pixels(&mut self, mut pixels: &[u8]) -> io::Result<()> {
+        let channels = self.qoi.colors.channels();
+
+        if self.partial_len > 0 {
+            let len = (channels - self.partial_len).min(pixels.len());
+            self.partial[self.partial_len..][..len].copy_from_slice(&pixels[..len]);
+            self.partial_len += len;
+            pixels = &pixels[len..];
+
+            if self.partial_len < channels {
+                return Ok(());
+            }
+
+            let partial = self.partial;
+            self.partial_len = 0;
+            self.encode(&partial[..channels])?;
+        }
+
+        let whole = pixels.len() - pixels.len() % channels;
+        for chunk in pixels[..whole].chunks(CHUNK * channels) {
+            self.encode(chunk)?;
+        }
+
+        let tail = &pixels[whole..];
+        self.partial[..tail.len()].copy_from_slice(tail);
+        self.partial_len = tail.len();
+
+        Ok(())
+    }
+
+    fn encode(&mut self, pixels: &[u8]) -> io::Result<()> {
+        let channels = self.qoi.colors.channels();
+        let left = self.qoi.width as u64 * self.qoi.height as u64 - self.state.pixels();
+
+        if (pixels.len() / channels) as u64 > left {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "More pixels written than image has",
+            ));
+        }
+
+        let size = self
+            .qoi
+            .encode_with_state(&mut self.state, pixels, &mut self.buffer)
+            .map_err(invalid_input)?;
+
+        self.writer.write_all(&self.buffer[..size])
+    }
+
+    /// Finishes encoding and returns underlying writer.\
+    /// Pending run and the end marker are written when last pixel is encoded.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] wrapping [`EncodeError::NotEnoughPixelData`]
+    /// if not all pixels of the image were written.
+    pub fn finish(mut self) -> io::Result<W> {
+        let total = self.qoi.width as u64 * self.qoi.height as u64;
+        if self.state.pixels() < total || self.partial_len > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                EncodeError::NotEnoughPixelData,
+            ));
+        }
+
+        if total == 0 {
+            let mut padding = [0; QOI_PADDING];
+            padding[QOI_PADDING - 1] = 1;
+            self.writer.write_all(&padding)?;
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W> Write for QoiWriteEncoder<W>
+where
+    W: Write,
+{
+    /// Same as [`QoiWriteEncoder::write_pixels`].
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_pixels(buf)?;
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Decoder that reads QOI image from [`Read`] incrementally.\
+/// Header is read on creation, pixels are decoded on demand
+/// with only small part of encoded image kept in memory.
+#[derive(Debug)]
+pub struct QoiReadDecoder<R> {
+    reader: R,
+    qoi: Qoi,
+    state: DecState,
+    buffer: Vec<u8>,
+    start: usize,
+    end: usize,
+    eof: bool,
+}
+
+/// Maximum size of single encoded pixel.
+const MAX_PIXEL_SIZE: usize = 5;
+
+impl<R> QoiReadDecoder<R>
+where
+    R: Read,
+{
+    /// Returns new decoder reading image from `reader`.\
+    /// Reads and validates header.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] wrapping [`DecodeError`] if header is invalid.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let qoi = Qoi::decode_header_from(&mut reader)?;
+
+        Ok(QoiReadDecoder {
+            reader,
+            qoi,
+            state: DecState::new(),
+            buffer: vec![0; CHUNK * MAX_PIXEL_SIZE],
+            start: 0,
+            end: 0,
+            eof: false,
+        })
+    }
+
+    /// Returns header of the image.
+    #[inline]
+    pub fn qoi(&self) -> Qoi {
+        self.qoi
+    }
+
+    /// Returns number of pixels decoded so far.
+    #[inline]
+    pub fn pixels(&self) -> u64 {
+        self.state.pixels()
+    }
+
+    /// Decode next raw RGB or RGBA pixels into `output` slice.\
+    /// Up to `output.len() / channels` pixels are decoded.
+    ///
+    /// On success this function returns `Ok(count)` with `count` of pixels decoded.
+    /// Zero is returned when all pixels of the image are decoded.\
+    /// Fails with [`io::ErrorKind::InvalidData`] wrapping [`DecodeError`] if image data is invalid or truncated.
+    pub fn read_pixels(&mut self, output: &mut [u8]) -> io::Result<usize> {
+        let channels = self.qoi.colors.channels();
+        let left = self.qoi.width as u64 * self.qoi.height as u64 - self.state.pixels();
+        let wanted = (output.len() / channels).min(CHUNK) as u64;
+        let wanted = wanted.min(left) as usize;
+
+        if wanted == 0 {
+            return Ok(0);
+        }
+
+        // Unless stream ends, all encoded pixels are buffered.
+        self.fill(wanted * MAX_PIXEL_SIZE)?;
+
+        let offset = self.state.offset();
+        self.qoi
+            .decode_with_state(
+                &mut self.state,
+                &self.buffer[self.start..self.end],
+                &mut output[..wanted * channels],
+            )
+            .map_err(invalid_data)?;
+        self.start += (self.state.offset() - offset) as usize;
+
+        Ok(wanted)
+    }
+
+    /// Reads from underlying reader until at least `size` bytes are buffered or end of stream is reached.
+    fn fill(&mut self, size: usize) -> io::Result<()> {
+        if self.end - self.start >= size {
+            return Ok(());
+        }
+
+        self.buffer.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+
+        while !self.eof && self.end < size {
+            match self.reader.read(&mut self.buffer[self.end..]) {
+                Ok(0) => self.eof = true,
+                Ok(n) => self.end += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns underlying reader.\
+    /// Bytes read ahead of the decoded pixels are lost.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl Qoi {
+    /// Reads exactly 14 bytes of QOI header from `reader` and decodes it.\
+    /// Nothing past the header is read.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] wrapping [`DecodeError`] if header is invalid
+    /// and with [`io::ErrorKind::UnexpectedEof`] if reader ends before the end of the header.
+    pub fn decode_header_from(mut reader: impl Read) -> io::Result<Self> {
+        let mut header = [0; QOI_HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+
+        Qoi::decode_header(&header).map_err(invalid_data)
+    }
+
+    /// Reads and decodes QOI image from file at `path`.\
+    /// Decoded raw RGB or RGBA pixels are written into allocated `Vec`.
+    ///
+    /// On success this function returns `Ok((qoi, vec))` with `qoi` describing image dimensions and color space and `vec` containing raw pixels data.\
+    /// Fails with [`io::ErrorKind::InvalidData`] wrapping [`DecodeError`] if file does not contain valid QOI image.
+    pub fn decode_file(path: impl AsRef<Path>) -> io::Result<(Self, Vec<u8>)> {
+        let bytes = std::fs::read(path)?;
+        Self::decode_alloc(&bytes).map_err(invalid_data)
+    }
+
+    /// Encodes raw RGB or RGBA pixels into a QOI image and writes it into file at `path`.\
+    /// File is created if it does not exist and truncated otherwise.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] wrapping [`EncodeError`] if pixels cannot be encoded.
+    pub fn encode_file(&self, path: impl AsRef<Path>, pixels: &[u8]) -> io::Result<()> {
+        let bytes = self.encode_alloc(pixels).map_err(invalid_input)?;
+
+        std::fs::write(path, bytes)
+    }
+}
+
+pub(crate) fn invalid_data(err: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+pub(crate) fn invalid_input(err: EncodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err)
+}
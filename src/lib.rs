@@ -144,7 +144,19 @@
 //! 8-bit green channel value\
 //! 8-bit  blue channel value\
 //! 8-bit alpha channel value
-#![forbid(unsafe_code)]
+//!
+//! # Panics
+//!
+//! [`Qoi::decode_header`], [`Qoi::decode`], [`Qoi::decode_range`], [`Qoi::encode`] and [`Qoi::encode_range`]
+//! never panic on any input in builds without debug assertions,
+//! so untrusted images can be decoded in processes that abort on panic.\
+//! Functions that allocate may still abort if allocation fails.\
+//! This is verified at link time by `cargo test --profile no-panic --test no_panic`.
+#![cfg_attr(
+    not(any(feature = "bytemuck", feature = "unsafe-perf")),
+    forbid(unsafe_code)
+)]
+#![cfg_attr(any(feature = "bytemuck", feature = "unsafe-perf"), deny(unsafe_code))]
 #![deny(missing_copy_implementations)]
 #![deny(missing_debug_implementations)]
 #![cfg_attr(not(feature = "std"), no_std)]
@@ -152,16 +164,95 @@
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
-use core::{
-    convert::TryInto,
-    fmt::{self, Display},
-};
+use core::fmt::{self, Display};
 
+mod bytes;
+mod concat;
 mod decode;
+mod decoder;
 mod encode;
+mod encoder;
+mod float;
+mod limits;
+mod options;
+mod packed;
+mod planar;
+mod region;
+mod rows;
+mod sink;
+mod source;
+mod state;
+mod typed;
+
+#[cfg(feature = "alloc")]
+mod data_uri;
+
+#[cfg(feature = "std")]
+mod parallel;
+
+#[cfg(feature = "std")]
+mod io;
+
+#[cfg(feature = "futures")]
+mod futures;
+
+#[cfg(feature = "tokio")]
+mod tokio;
+
+#[cfg(feature = "image")]
+mod image;
+
+#[cfg(feature = "rgb")]
+mod rgb;
+
+#[cfg(feature = "unsafe-perf")]
+mod unchecked;
+
+pub mod alpha;
+pub mod ops;
+pub mod pipeline;
+pub mod stats;
+
+#[cfg(feature = "ext")]
+pub mod ext;
+
+#[cfg(feature = "experimental")]
+pub mod experiment;
+
+#[cfg(feature = "ext")]
+pub mod meta;
+
+#[cfg(feature = "alloc")]
+pub mod palette;
+
+#[cfg(feature = "viewer")]
+pub mod viewer;
 
-pub use decode::DecodeError;
+pub use concat::Concatenated;
+pub use decode::{DecodeError, RangeProgress};
+pub use decoder::{Decoder, Progress};
 pub use encode::EncodeError;
+pub use encoder::Encoder;
+pub use limits::Limits;
+pub use options::{ChannelOrder, Channels, DecodeOptions, EncodeOptions};
+pub use packed::{Packed16Layout, PackedLayout};
+pub use region::Rect;
+pub use sink::PixelSink;
+pub use source::PixelSource;
+pub use state::{DecState, EncState};
+pub use typed::EncodePixel;
+
+#[cfg(feature = "std")]
+pub use io::{QoiReadDecoder, QoiWriteEncoder};
+
+#[cfg(feature = "std")]
+pub use parallel::BandOffset;
+
+#[cfg(feature = "tokio")]
+pub use tokio::QoiCodec;
+
+#[cfg(feature = "image")]
+pub use image::{DynamicImageQoiExt, QoiDecoder, QoiEncoder};
 
 const QOI_OP_INDEX: u8 = 0x00; /* 00xxxxxx */
 const QOI_OP_DIFF: u8 = 0x40; /* 01xxxxxx */
@@ -173,51 +264,84 @@ const QOI_OP_RGBA: u8 = 0xff; /* 11111111 */
 const QOI_MAGIC: u32 = u32::from_be_bytes(*b"qoif");
 const QOI_HEADER_SIZE: usize = 14;
 const QOI_PADDING: usize = 8;
+const QOI_END_MARKER: [u8; QOI_PADDING] = [0, 0, 0, 0, 0, 0, 0, 1];
 
 /// Trait for pixel types.
 /// Supports byte operations, channels accessing and modifying.
+///
+/// Implemented for `[u8; 3]` and `[u8; 4]` arrays, which are the pixel representation
+/// used by both [`Qoi::encode_range`] and [`Qoi::decode_range`].\
+/// Use `From` conversions to move between arrays and [`Rgb`] / [`Rgba`] structs.
 pub trait Pixel: Copy + Eq {
+    /// Whether pixel type has alpha channel.
     const HAS_ALPHA: bool;
 
+    /// Returns pixel with all channels set to zero.
     fn new() -> Self;
 
+    /// Returns black pixel with alpha set to `255`, if pixel type has alpha channel.\
+    /// This is the initial previous pixel of QOI encoder and decoder.
     fn new_opaque() -> Self;
 
+    /// Reads channels from `bytes`.\
+    /// `bytes` must be exactly one pixel long.
     fn read(&mut self, bytes: &[u8]);
 
+    /// Writes channels into `bytes`.\
+    /// `bytes` must be exactly one pixel long.
     fn write(&self, bytes: &mut [u8]);
 
+    /// Returns wrapping difference of color channels from `prev` pixel.
     fn var(&self, prev: &Self) -> Var;
 
+    /// Returns color channels.
     fn rgb(&self) -> [u8; 3];
 
+    /// Returns all channels.\
+    /// Alpha is `255` for pixel types without alpha channel.
     fn rgba(&self) -> [u8; 4];
 
+    /// Returns red channel.
     fn r(&self) -> u8;
 
+    /// Returns green channel.
     fn g(&self) -> u8;
 
+    /// Returns blue channel.
     fn b(&self) -> u8;
 
+    /// Returns alpha channel.\
+    /// Always `255` for pixel types without alpha channel.
     fn a(&self) -> u8;
 
+    /// Sets red channel.
     fn set_r(&mut self, r: u8);
 
+    /// Sets green channel.
     fn set_g(&mut self, g: u8);
 
+    /// Sets blue channel.
     fn set_b(&mut self, b: u8);
 
+    /// Sets alpha channel.\
+    /// Ignored by pixel types without alpha channel.
     fn set_a(&mut self, a: u8);
 
+    /// Sets color channels.
     fn set_rgb(&mut self, r: u8, g: u8, b: u8);
 
+    /// Sets all channels.\
+    /// Alpha is ignored by pixel types without alpha channel.
     fn set_rgba(&mut self, r: u8, g: u8, b: u8, a: u8);
 
+    /// Adds wrapping offsets to color channels.
     fn add_rgb(&mut self, r: u8, g: u8, b: u8);
 
+    /// Returns position of the pixel in the QOI color index.
     fn hash(&self) -> u8;
 }
 
+/// RGB pixel.
 impl Pixel for [u8; 3] {
     const HAS_ALPHA: bool = false;
 
@@ -343,14 +467,7 @@ impl Pixel for [u8; 3] {
     }
 }
 
-// /// Four channel pixel type.
-// /// Typically channels are Red, Green, Blue and Alpha.
-// #[repr(transparent)]
-// #[derive(Clone, Copy, PartialEq, Eq)]
-// pub struct Rgba {
-//     pub rgba: [u8; 4],
-// }
-
+/// RGBA pixel.
 impl Pixel for [u8; 4] {
     const HAS_ALPHA: bool = true;
 
@@ -366,12 +483,7 @@ impl Pixel for [u8; 4] {
 
     #[inline]
     fn read(&mut self, bytes: &[u8]) {
-        match bytes.try_into() {
-            Ok(rgba) => {
-                *self = rgba;
-            }
-            _ => unreachable(),
-        }
+        self.copy_from_slice(bytes);
     }
 
     #[inline]
@@ -479,6 +591,79 @@ impl Pixel for [u8; 4] {
     }
 }
 
+/// Three channel pixel type.
+/// Channels are Red, Green and Blue.\
+/// Implements `bytemuck::Pod` with `bytemuck` feature enabled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl From<[u8; 3]> for Rgb {
+    #[inline]
+    fn from([r, g, b]: [u8; 3]) -> Self {
+        Rgb { r, g, b }
+    }
+}
+
+impl From<Rgb> for [u8; 3] {
+    #[inline]
+    fn from(Rgb { r, g, b }: Rgb) -> Self {
+        [r, g, b]
+    }
+}
+
+// SAFETY: `Rgb` is `repr(C)` with three `u8` fields, so all-zero bytes are valid `Rgb`.
+#[cfg(feature = "bytemuck")]
+#[allow(unsafe_code)]
+unsafe impl bytemuck::Zeroable for Rgb {}
+
+// SAFETY: `Rgb` is `repr(C)` with three `u8` fields, so it has no padding,
+// any bit pattern is valid and its size is 3 bytes with alignment of 1.
+#[cfg(feature = "bytemuck")]
+#[allow(unsafe_code)]
+unsafe impl bytemuck::Pod for Rgb {}
+
+/// Four channel pixel type.
+/// Channels are Red, Green, Blue and Alpha.\
+/// Implements `bytemuck::Pod` with `bytemuck` feature enabled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl From<[u8; 4]> for Rgba {
+    #[inline]
+    fn from([r, g, b, a]: [u8; 4]) -> Self {
+        Rgba { r, g, b, a }
+    }
+}
+
+impl From<Rgba> for [u8; 4] {
+    #[inline]
+    fn from(Rgba { r, g, b, a }: Rgba) -> Self {
+        [r, g, b, a]
+    }
+}
+
+// SAFETY: `Rgba` is `repr(C)` with four `u8` fields, so all-zero bytes are valid `Rgba`.
+#[cfg(feature = "bytemuck")]
+#[allow(unsafe_code)]
+unsafe impl bytemuck::Zeroable for Rgba {}
+
+// SAFETY: `Rgba` is `repr(C)` with four `u8` fields, so it has no padding,
+// any bit pattern is valid and its size is 4 bytes with alignment of 1.
+#[cfg(feature = "bytemuck")]
+#[allow(unsafe_code)]
+unsafe impl bytemuck::Pod for Rgba {}
+
 /// Color variance value.
 /// Wrapping difference between two pixels.
 #[derive(Clone, Copy, Debug)]
@@ -497,7 +682,7 @@ impl Var {
         let b = self.b.wrapping_add(2);
 
         match r | g | b {
-            0x00..=0x03 => Some(QOI_OP_DIFF | (r << 4) as u8 | (g << 2) as u8 | b as u8),
+            0x00..=0x03 => Some(QOI_OP_DIFF | (r << 4) | (g << 2) | b),
             _ => None,
         }
     }
@@ -551,6 +736,18 @@ impl Colors {
             Colors::Rgba | Colors::SrgbLinA => 4,
         }
     }
+
+    /// Returns color space with the same color channels encoding,
+    /// with alpha channel if `alpha` is `true` and without one otherwise.
+    #[inline]
+    pub const fn with_alpha(&self, alpha: bool) -> Self {
+        match (self, alpha) {
+            (Colors::Srgb | Colors::SrgbLinA, false) => Colors::Srgb,
+            (Colors::Srgb | Colors::SrgbLinA, true) => Colors::SrgbLinA,
+            (Colors::Rgb | Colors::Rgba, false) => Colors::Rgb,
+            (Colors::Rgb | Colors::Rgba, true) => Colors::Rgba,
+        }
+    }
 }
 
 /// QOI descriptor value.\
@@ -587,12 +784,3 @@ const fn unlikely(b: bool) -> bool {
     }
     b
 }
-
-/// Next best thing after `core::hint::unreachable_unchecked()`
-/// If happens to be called this will stall CPU, instead of causing UB.
-#[inline]
-#[cold]
-#[allow(clippy::empty_loop)]
-const fn unreachable() -> ! {
-    loop {}
-}
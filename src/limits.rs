@@ -0,0 +1,88 @@
+use super::*;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Limits on dimensions and decoded size of the image.\
+/// Header is checked against limits before output is allocated,
+/// so small file claiming huge dimensions is rejected without allocating gigabytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// Maximum width of the image in pixels.
+    pub max_width: u32,
+
+    /// Maximum height of the image in pixels.
+    pub max_height: u32,
+
+    /// Maximum number of pixels in the image.
+    pub max_pixels: u64,
+
+    /// Maximum bytes size of decoded pixels.
+    pub max_output_bytes: usize,
+}
+
+impl Limits {
+    /// Default limits.\
+    /// Allow images up to `16384` pixels in each dimension and up to `64` megapixels,
+    /// decoded into at most `256` MiB.
+    pub const DEFAULT: Self = Limits {
+        max_width: 16384,
+        max_height: 16384,
+        max_pixels: 64 * 1024 * 1024,
+        max_output_bytes: 256 * 1024 * 1024,
+    };
+
+    /// Limits that accept any image with decoded size fitting into `usize`.
+    pub const NONE: Self = Limits {
+        max_width: u32::MAX,
+        max_height: u32::MAX,
+        max_pixels: u64::MAX,
+        max_output_bytes: usize::MAX,
+    };
+
+    /// Checks image header against limits.
+    ///
+    /// Fails with [`DecodeError::LimitsExceeded`] if image exceeds any limit
+    /// and with [`DecodeError::TooLarge`] if decoded size does not fit into `usize`.
+    #[inline]
+    pub const fn check(&self, qoi: &Qoi) -> Result<(), DecodeError> {
+        if qoi.width > self.max_width
+            || qoi.height > self.max_height
+            || qoi.width as u64 * qoi.height as u64 > self.max_pixels
+        {
+            return Err(DecodeError::LimitsExceeded);
+        }
+
+        match qoi.checked_decoded_size() {
+            None => Err(DecodeError::TooLarge),
+            Some(size) if size > self.max_output_bytes => Err(DecodeError::LimitsExceeded),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+impl Default for Limits {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice, rejecting images that exceed `limits`.\
+    /// Header is checked before output is allocated,
+    /// which makes this function suitable for decoding untrusted uploads.\
+    /// Decoded raw RGB or RGBA pixels are written into allocated `Vec`.
+    ///
+    /// On success this function returns `Ok((qoi, vec))` with `qoi` describing image dimensions and color space and `vec` containing raw pixels data.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    pub fn decode_alloc_limited(
+        bytes: &[u8],
+        limits: &Limits,
+    ) -> Result<(Self, Vec<u8>), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        limits.check(&qoi)?;
+        Self::decode_alloc(bytes)
+    }
+}
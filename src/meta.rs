@@ -0,0 +1,329 @@
+//! Image metadata sidecar.
+//!
+//! Sidecar is a sequence of records stored either in a separate file next to the image
+//! or embedded into image with extended header that has [`Capabilities::METADATA`](crate::ext::Capabilities::METADATA) flag.
+//! ```rust
+//! #[repr(C)]
+//! struct Record {
+//!     tag: [u8; 4], // record kind
+//!     len: u32,     // payload length in bytes (BE)
+//!     // followed by `len` bytes of payload
+//! }
+//! ```
+//! Readers skip records with unknown tags.
+
+use core::convert::TryInto;
+
+use super::*;
+
+use ext::ExtImage;
+
+const RECORD_HEADER_SIZE: usize = 8;
+
+/// Iterator over records in metadata sidecar.\
+/// Yields tag and payload of each record.
+#[derive(Clone, Debug)]
+pub struct Records<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Records<'a> {
+    /// Returns iterator over records in metadata sidecar.
+    #[inline]
+    pub fn new(sidecar: &'a [u8]) -> Self {
+        Records { bytes: sidecar }
+    }
+
+    /// Finds payload of the first record with specified tag.
+    #[inline]
+    pub fn find_tag(sidecar: &'a [u8], tag: [u8; 4]) -> Result<Option<&'a [u8]>, DecodeError> {
+        for record in Records::new(sidecar) {
+            let (t, payload) = record?;
+            if t == tag {
+                return Ok(Some(payload));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<([u8; 4], &'a [u8]), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        if self.bytes.len() < RECORD_HEADER_SIZE {
+            self.bytes = &[];
+            return Some(Err(DecodeError::InvalidMetadata));
+        }
+
+        let tag = self.bytes[0..4].try_into().unwrap();
+        let len = u32::from_be_bytes(self.bytes[4..8].try_into().unwrap()) as usize;
+
+        match self.bytes[RECORD_HEADER_SIZE..].get(..len) {
+            None => {
+                self.bytes = &[];
+                Some(Err(DecodeError::InvalidMetadata))
+            }
+            Some(payload) => {
+                self.bytes = &self.bytes[RECORD_HEADER_SIZE + len..];
+                Some(Ok((tag, payload)))
+            }
+        }
+    }
+}
+
+/// Writes single record into `output`.\
+/// Sidecar is formed by writing records one after another.
+///
+/// On success this function returns `Ok(size)` with `size` of the record.\
+/// On failure this function returns `Err(err)` with `err` describing cause of the error.
+pub fn write_record(tag: [u8; 4], payload: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+    let size = RECORD_HEADER_SIZE + payload.len();
+    if output.len() < size || payload.len() > u32::MAX as usize {
+        return Err(EncodeError::OutputIsTooSmall);
+    }
+
+    output[0..4].copy_from_slice(&tag);
+    output[4..8].copy_from_slice(&(payload.len() as u32).to_be_bytes());
+    output[RECORD_HEADER_SIZE..size].copy_from_slice(payload);
+    Ok(size)
+}
+
+/// Nine-patch border insets.\
+/// Defines stretchable area of the image as the part inside insets.
+/// Corners are never scaled, edges are stretched along one axis and center along both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NinePatch {
+    /// Width of the left border in pixels.
+    pub left: u32,
+
+    /// Height of the top border in pixels.
+    pub top: u32,
+
+    /// Width of the right border in pixels.
+    pub right: u32,
+
+    /// Height of the bottom border in pixels.
+    pub bottom: u32,
+}
+
+impl NinePatch {
+    /// Tag of the nine-patch record.
+    pub const TAG: [u8; 4] = *b"9pch";
+
+    const SIZE: usize = 16;
+
+    /// Writes nine-patch record into `output`.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the record.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn write(&self, output: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut payload = [0; Self::SIZE];
+        payload[0..4].copy_from_slice(&self.left.to_be_bytes());
+        payload[4..8].copy_from_slice(&self.top.to_be_bytes());
+        payload[8..12].copy_from_slice(&self.right.to_be_bytes());
+        payload[12..16].copy_from_slice(&self.bottom.to_be_bytes());
+        write_record(Self::TAG, &payload, output)
+    }
+
+    /// Reads nine-patch insets from metadata sidecar.\
+    /// Returns `Ok(None)` if sidecar has no nine-patch record.
+    pub fn read(sidecar: &[u8]) -> Result<Option<Self>, DecodeError> {
+        let payload = match Records::find_tag(sidecar, Self::TAG)? {
+            None => return Ok(None),
+            Some(payload) => payload,
+        };
+
+        if payload.len() != Self::SIZE {
+            return Err(DecodeError::InvalidMetadata);
+        }
+
+        let field = |i: usize| u32::from_be_bytes(payload[i * 4..][..4].try_into().unwrap());
+
+        Ok(Some(NinePatch {
+            left: field(0),
+            top: field(1),
+            right: field(2),
+            bottom: field(3),
+        }))
+    }
+}
+
+/// Decode image with extended header into `output` slice
+/// and read nine-patch insets from embedded metadata.
+///
+/// On success this function returns `Ok((qoi, insets))` with `qoi` describing image dimensions and color space
+/// and `insets` of the nine-patch if image has them.\
+/// On failure this function returns `Err(err)` with `err` describing cause of the error.
+pub fn decode_nine_patch(
+    bytes: &[u8],
+    output: &mut [u8],
+) -> Result<(Qoi, Option<NinePatch>), DecodeError> {
+    let image = ExtImage::parse(bytes)?;
+    let insets = NinePatch::read(image.metadata)?;
+    image.decode(output)?;
+    Ok((image.header.qoi, insets))
+}
+
+/// Sprite-sheet frame table.\
+/// Maps frame names to regions of the image.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameTable<'a> {
+    payload: &'a [u8],
+}
+
+impl<'a> FrameTable<'a> {
+    /// Tag of the frame table record.
+    pub const TAG: [u8; 4] = *b"frms";
+
+    /// Writes frame table record into `output`.\
+    /// Fails with [`EncodeError::InvalidMetadata`] if any frame name is longer than 255 bytes,
+    /// nothing is written into `output` in this case.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the record.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn write(frames: &[(&str, Rect)], output: &mut [u8]) -> Result<usize, EncodeError> {
+        if frames.iter().any(|(name, _)| name.len() > 255) {
+            return Err(EncodeError::InvalidMetadata);
+        }
+
+        let payload_len = frames
+            .iter()
+            .map(|(name, _)| 1 + name.len() + 16)
+            .sum::<usize>();
+
+        if output.len() < RECORD_HEADER_SIZE + payload_len || payload_len > u32::MAX as usize {
+            return Err(EncodeError::OutputIsTooSmall);
+        }
+
+        output[0..4].copy_from_slice(&Self::TAG);
+        output[4..8].copy_from_slice(&(payload_len as u32).to_be_bytes());
+
+        let mut rest = &mut output[RECORD_HEADER_SIZE..];
+        for (name, rect) in frames {
+            rest[0] = name.len() as u8;
+            rest[1..][..name.len()].copy_from_slice(name.as_bytes());
+            rest = &mut rest[1 + name.len()..];
+
+            rest[0..4].copy_from_slice(&rect.x.to_be_bytes());
+            rest[4..8].copy_from_slice(&rect.y.to_be_bytes());
+            rest[8..12].copy_from_slice(&rect.width.to_be_bytes());
+            rest[12..16].copy_from_slice(&rect.height.to_be_bytes());
+            rest = &mut rest[16..];
+        }
+
+        Ok(RECORD_HEADER_SIZE + payload_len)
+    }
+
+    /// Reads frame table from metadata sidecar.\
+    /// Returns `Ok(None)` if sidecar has no frame table record.
+    #[inline]
+    pub fn read(sidecar: &'a [u8]) -> Result<Option<Self>, DecodeError> {
+        Ok(Records::find_tag(sidecar, Self::TAG)?.map(|payload| FrameTable { payload }))
+    }
+
+    /// Returns iterator over frames in the table.
+    #[inline]
+    pub fn frames(&self) -> Frames<'a> {
+        Frames {
+            bytes: self.payload,
+        }
+    }
+
+    /// Finds region of the frame with specified name.
+    pub fn find(&self, name: &str) -> Result<Option<Rect>, DecodeError> {
+        for frame in self.frames() {
+            let (n, rect) = frame?;
+            if n == name {
+                return Ok(Some(rect));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Iterator over frames in [`FrameTable`].\
+/// Yields name and region of each frame.
+#[derive(Clone, Debug)]
+pub struct Frames<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Result<(&'a str, Rect), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&len, tail) = self.bytes.split_first()?;
+        let len = len as usize;
+
+        if tail.len() < len + 16 {
+            self.bytes = &[];
+            return Some(Err(DecodeError::InvalidMetadata));
+        }
+
+        let name = match core::str::from_utf8(&tail[..len]) {
+            Err(_) => {
+                self.bytes = &[];
+                return Some(Err(DecodeError::InvalidMetadata));
+            }
+            Ok(name) => name,
+        };
+
+        let field = |i: usize| u32::from_be_bytes(tail[len + i * 4..][..4].try_into().unwrap());
+        let rect = Rect {
+            x: field(0),
+            y: field(1),
+            width: field(2),
+            height: field(3),
+        };
+
+        self.bytes = &tail[len + 16..];
+        Some(Ok((name, rect)))
+    }
+}
+
+/// Decode single frame of the sprite-sheet image with extended header into `output` slice.\
+/// Frame region is looked up by `name` in the frame table from embedded metadata.
+/// Only pixels up to the end of the frame are decoded.
+///
+/// Frames can't be decoded from images with `Capabilities::PAETH_FILTER` flag,
+/// since unfiltering requires all pixels above and to the left of the frame.
+/// Such images are rejected with [`DecodeError::UnsupportedCapabilities`].
+///
+/// On success this function returns `Ok(Some(rect))` with `rect` of the decoded frame
+/// or `Ok(None)` if there is no frame with such name.\
+/// On failure this function returns `Err(err)` with `err` describing cause of the error.
+pub fn decode_frame(
+    bytes: &[u8],
+    name: &str,
+    output: &mut [u8],
+) -> Result<Option<Rect>, DecodeError> {
+    let image = ExtImage::parse(bytes)?;
+
+    #[cfg(feature = "experimental")]
+    if image
+        .header
+        .capabilities
+        .contains(ext::Capabilities::PAETH_FILTER)
+    {
+        return Err(DecodeError::UnsupportedCapabilities);
+    }
+
+    let rect = match FrameTable::read(image.metadata)? {
+        None => return Ok(None),
+        Some(table) => match table.find(name)? {
+            None => return Ok(None),
+            Some(rect) => rect,
+        },
+    };
+
+    image
+        .header
+        .qoi
+        .decode_region_skip_header(image.data, rect, output)?;
+    Ok(Some(rect))
+}
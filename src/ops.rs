@@ -0,0 +1,290 @@
+//! Low-level access to QOI ops.
+//!
+//! [`Iter`] parses encoded stream into [`Op`] values without producing pixels
+//! and [`Writer`] emits validated ops.
+//! Useful to analyze, transform and debug encoded streams and to build custom encoders.
+
+use super::*;
+
+/// Single QOI op.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Op {
+    /// `QOI_OP_INDEX`. Pixel from color index at specified position in `0..64`.
+    Index(u8),
+
+    /// `QOI_OP_DIFF`. Small difference from previous pixel.\
+    /// Each channel difference is in `-2..=1` range.
+    Diff {
+        /// Red channel difference.
+        dr: i8,
+
+        /// Green channel difference.
+        dg: i8,
+
+        /// Blue channel difference.
+        db: i8,
+    },
+
+    /// `QOI_OP_LUMA`. Difference from previous pixel relative to green channel difference.\
+    /// `dg` is in `-32..=31` range, `dr_dg` and `db_dg` are in `-8..=7` range.
+    Luma {
+        /// Green channel difference.
+        dg: i8,
+
+        /// Red channel difference minus green channel difference.
+        dr_dg: i8,
+
+        /// Blue channel difference minus green channel difference.
+        db_dg: i8,
+    },
+
+    /// `QOI_OP_RUN`. Previous pixel repeated specified number of times in `1..=62`.
+    Run(u8),
+
+    /// `QOI_OP_RGB`. Color channels with alpha of previous pixel.
+    Rgb(Rgb),
+
+    /// `QOI_OP_RGBA`. Color and alpha channels.
+    Rgba(Rgba),
+}
+
+impl Op {
+    /// Returns size of encoded op in bytes.
+    #[inline]
+    pub const fn size(&self) -> usize {
+        match self {
+            Op::Index(_) | Op::Diff { .. } | Op::Run(_) => 1,
+            Op::Luma { .. } => 2,
+            Op::Rgb(_) => 4,
+            Op::Rgba(_) => 5,
+        }
+    }
+
+    /// Returns number of pixels produced by the op.
+    #[inline]
+    pub const fn pixels(&self) -> usize {
+        match self {
+            Op::Run(run) => *run as usize,
+            _ => 1,
+        }
+    }
+
+    /// Parses op from the start of `bytes`.
+    ///
+    /// On success this function returns `Ok(op)` with parsed op.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn parse(bytes: &[u8]) -> Result<Self, DecodeError> {
+        match *bytes {
+            [QOI_OP_RGB, r, g, b, ..] => Ok(Op::Rgb(Rgb { r, g, b })),
+            [QOI_OP_RGBA, r, g, b, a, ..] => Ok(Op::Rgba(Rgba { r, g, b, a })),
+            [QOI_OP_RGB, ..] | [QOI_OP_RGBA, ..] => Err(DecodeError::NotEnoughData),
+            [b1 @ 0x00..=0x3f, ..] => Ok(Op::Index(b1)),
+            [b1 @ 0x40..=0x7f, ..] => Ok(Op::Diff {
+                dr: ((b1 >> 4) & 0x03) as i8 - 2,
+                dg: ((b1 >> 2) & 0x03) as i8 - 2,
+                db: (b1 & 0x03) as i8 - 2,
+            }),
+            [b1 @ 0x80..=0xbf, b2, ..] => Ok(Op::Luma {
+                dg: (b1 & 0x3f) as i8 - 32,
+                dr_dg: (b2 >> 4) as i8 - 8,
+                db_dg: (b2 & 0x0f) as i8 - 8,
+            }),
+            [0x80..=0xbf] => Err(DecodeError::NotEnoughData),
+            [b1, ..] => Ok(Op::Run((b1 & 0x3f) + 1)),
+            [] => Err(DecodeError::NotEnoughData),
+        }
+    }
+}
+
+/// Returns size of the op starting with `b1`.
+#[inline]
+pub(crate) fn op_size(b1: u8) -> usize {
+    match b1 {
+        QOI_OP_RGB => 4,
+        QOI_OP_RGBA => 5,
+        0x80..=0xbf => 2,
+        _ => 1,
+    }
+}
+
+/// Returns number of pixels produced by the op starting with `b1`.
+#[inline]
+pub(crate) fn op_pixels(b1: u8) -> usize {
+    match b1 {
+        0xc0..=0xfd => (b1 & 0x3f) as usize + 1,
+        _ => 1,
+    }
+}
+
+/// Iterator over ops of encoded stream.\
+/// Yields `Err` once if stream ends with incomplete op.
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+    bytes: &'a [u8],
+    pixels: Option<u64>,
+}
+
+impl<'a> Iter<'a> {
+    /// Returns iterator over all ops in `bytes`.\
+    /// `bytes` does not include QOI header.
+    /// Note that end marker is parsed as ops too.
+    #[inline]
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Iter {
+            bytes,
+            pixels: None,
+        }
+    }
+
+    /// Returns iterator over ops of the image that stops after the last pixel.\
+    /// `bytes` does not include QOI header. Uses provided `Qoi` value instead.
+    #[inline]
+    pub fn with_header(qoi: &Qoi, bytes: &'a [u8]) -> Self {
+        Iter {
+            bytes,
+            pixels: Some(qoi.width as u64 * qoi.height as u64),
+        }
+    }
+
+    /// Reads header and returns iterator over ops of the image that stops after the last pixel.
+    #[inline]
+    pub fn parse(bytes: &'a [u8]) -> Result<(Qoi, Self), DecodeError> {
+        let qoi = Qoi::decode_header(bytes)?;
+        Ok((qoi, Iter::with_header(&qoi, &bytes[QOI_HEADER_SIZE..])))
+    }
+
+    /// Returns bytes that are not parsed yet.
+    #[inline]
+    pub fn remainder(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Result<Op, DecodeError>;
+
+    fn next(&mut self) -> Option<Result<Op, DecodeError>> {
+        match self.pixels {
+            Some(0) => return None,
+            None if self.bytes.is_empty() => return None,
+            _ => {}
+        }
+
+        match Op::parse(self.bytes) {
+            Ok(op) => {
+                self.bytes = &self.bytes[op.size()..];
+                if let Some(pixels) = &mut self.pixels {
+                    *pixels = pixels.saturating_sub(op.pixels() as u64);
+                }
+                Some(Ok(op))
+            }
+            Err(err) => {
+                self.bytes = &[];
+                self.pixels = Some(0);
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Maximum number of consecutive `QOI_OP_INDEX` ops to position `0`.\
+/// More would be indistinguishable from the end marker.
+const MAX_ZERO_INDICES: usize = 6;
+
+/// Writer that emits ops into output slice.\
+/// Rejects ops that cannot appear in valid QOI stream.
+#[derive(Debug)]
+pub struct Writer<'a> {
+    output: &'a mut [u8],
+    written: usize,
+    zero_indices: usize,
+}
+
+impl<'a> Writer<'a> {
+    /// Returns writer that emits ops into `output`.\
+    /// QOI header is not written.
+    #[inline]
+    pub fn new(output: &'a mut [u8]) -> Self {
+        Writer {
+            output,
+            written: 0,
+            zero_indices: 0,
+        }
+    }
+
+    /// Returns number of bytes written so far.
+    #[inline]
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Writes single op.
+    ///
+    /// Fails with [`EncodeError::InvalidOp`] if op values are out of range
+    /// or if op is 7th consecutive `QOI_OP_INDEX` to position `0`.
+    pub fn write(&mut self, op: Op) -> Result<(), EncodeError> {
+        let mut bytes = [0; 5];
+
+        match op {
+            Op::Index(index) if index < 64 => bytes[0] = QOI_OP_INDEX | index,
+            Op::Diff { dr, dg, db }
+                if (-2..=1).contains(&dr) && (-2..=1).contains(&dg) && (-2..=1).contains(&db) =>
+            {
+                bytes[0] =
+                    QOI_OP_DIFF | ((dr + 2) as u8) << 4 | ((dg + 2) as u8) << 2 | (db + 2) as u8;
+            }
+            Op::Luma { dg, dr_dg, db_dg }
+                if (-32..=31).contains(&dg)
+                    && (-8..=7).contains(&dr_dg)
+                    && (-8..=7).contains(&db_dg) =>
+            {
+                bytes[0] = QOI_OP_LUMA | (dg + 32) as u8;
+                bytes[1] = ((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8;
+            }
+            Op::Run(run) if (1..=62).contains(&run) => bytes[0] = QOI_OP_RUN | (run - 1),
+            Op::Rgb(Rgb { r, g, b }) => bytes[..4].copy_from_slice(&[QOI_OP_RGB, r, g, b]),
+            Op::Rgba(Rgba { r, g, b, a }) => {
+                bytes.copy_from_slice(&[QOI_OP_RGBA, r, g, b, a]);
+            }
+            _ => return Err(EncodeError::InvalidOp),
+        }
+
+        let zero_indices = match op {
+            Op::Index(0) => self.zero_indices + 1,
+            _ => 0,
+        };
+
+        if zero_indices > MAX_ZERO_INDICES {
+            return Err(EncodeError::InvalidOp);
+        }
+
+        let size = op.size();
+        match self.output.get_mut(self.written..self.written + size) {
+            None => return Err(EncodeError::OutputIsTooSmall),
+            Some(output) => output.copy_from_slice(&bytes[..size]),
+        }
+
+        self.written += size;
+        self.zero_indices = zero_indices;
+        Ok(())
+    }
+
+    /// Writes the end marker.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of all data written.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn finish(self) -> Result<usize, EncodeError> {
+        let output = match self
+            .output
+            .get_mut(self.written..self.written + QOI_PADDING)
+        {
+            None => return Err(EncodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        output[..QOI_PADDING - 1].fill(0);
+        output[QOI_PADDING - 1] = 1;
+
+        Ok(self.written + QOI_PADDING)
+    }
+}
@@ -0,0 +1,355 @@
+use super::*;
+
+use crate::{bytes::SliceSink, pipeline::PreOps};
+
+/// Layout of decoded pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Channels {
+    /// Three bytes per pixel, red, green and blue.
+    Rgb,
+
+    /// Four bytes per pixel, red, green, blue and alpha.
+    Rgba,
+}
+
+impl Channels {
+    /// Returns number of channels.
+    #[inline]
+    pub const fn count(&self) -> usize {
+        match self {
+            Channels::Rgb => 3,
+            Channels::Rgba => 4,
+        }
+    }
+}
+
+/// Order of channels in raw pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChannelOrder {
+    /// Red, green, blue and alpha.
+    Rgba,
+
+    /// Blue, green, red and alpha.\
+    /// Native layout of Windows GDI, Direct2D and many swapchain formats.
+    Bgra,
+
+    /// Alpha, red, green and blue.\
+    /// Same as [`ChannelOrder::Rgba`] for pixels without alpha channel.
+    Argb,
+
+    /// Alpha, blue, green and red.\
+    /// Same as [`ChannelOrder::Bgra`] for pixels without alpha channel.
+    Abgr,
+}
+
+impl Default for ChannelOrder {
+    #[inline]
+    fn default() -> Self {
+        ChannelOrder::Rgba
+    }
+}
+
+/// Options to control how decoded pixels are written into output.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DecodeOptions {
+    /// Background color to composite pixels over.\
+    /// When set, images with alpha channel are decoded into RGB output
+    /// with each pixel blended over this color according to its alpha.\
+    /// Blending is performed on encoded channel values, regardless of image color space.
+    pub matte: Option<Rgb>,
+
+    /// When set, images without alpha channel are decoded into RGBA output
+    /// with alpha of each pixel set to `255`.
+    pub expand_alpha: bool,
+
+    /// When set, images with alpha channel are decoded into RGB output
+    /// with alpha of each pixel discarded.\
+    /// Ignored if `matte` is set.
+    pub drop_alpha: bool,
+
+    /// Order of channels in output.\
+    /// Channels are reordered while pixels are written, without additional pass.
+    pub order: ChannelOrder,
+
+    /// When set, color channels of images with alpha channel are multiplied by alpha,
+    /// producing premultiplied output.\
+    /// Ignored if `matte` is set.
+    pub premultiply: bool,
+
+    /// When set, encoded image must be terminated with valid end marker,
+    /// otherwise [`DecodeError::InvalidEndMarker`] is returned.
+    /// Any bytes after the end marker cause [`DecodeError::TrailingData`].\
+    /// By default decoding stops after the last pixel, end marker is not checked and following bytes are ignored.
+    pub strict: bool,
+}
+
+impl DecodeOptions {
+    /// Returns number of channels in output for the image.
+    #[inline]
+    pub fn channels(&self, qoi: &Qoi) -> usize {
+        match qoi.colors.has_alpha() {
+            true if self.matte.is_some() || self.drop_alpha => 3,
+            false if self.expand_alpha => 4,
+            _ => qoi.colors.channels(),
+        }
+    }
+
+    /// Returns bytes size of the output for the image.\
+    /// Wraps around if size does not fit into `usize`, see [`DecodeOptions::checked_decoded_size`].
+    #[inline]
+    pub fn decoded_size(&self, qoi: &Qoi) -> usize {
+        qoi.width as usize * qoi.height as usize * self.channels(qoi)
+    }
+
+    /// Returns bytes size of the output for the image.\
+    /// Returns `None` if size does not fit into `usize`.
+    #[inline]
+    pub fn checked_decoded_size(&self, qoi: &Qoi) -> Option<usize> {
+        qoi.pixel_count().ok()?.checked_mul(self.channels(qoi))
+    }
+}
+
+/// Options to control heuristics of the encoder.\
+/// Every combination of options produces valid QOI image,
+/// allowing to trade encoding speed, output size and decoding speed per workload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EncodeOptions {
+    /// When set, single repeated pixel is encoded as `QOI_OP_INDEX` instead of `QOI_OP_RUN`.\
+    /// Both ops are one byte long, but index op is decoded slightly faster.\
+    /// Reference encoder always writes run op.
+    pub index_single_run: bool,
+
+    /// When set, pixels found in the index are encoded as `QOI_OP_INDEX`.\
+    /// Disabling lookup makes encoding of noisy images slightly faster
+    /// at the cost of larger output for images with few colors.
+    pub index_lookup: bool,
+
+    /// Maximum number of pixels encoded with single `QOI_OP_RUN`, clamped to `1..=62`.\
+    /// Long runs are split into ops of this length.
+    /// Lower values increase output size, but keep decoder from writing long runs at once.
+    pub max_run: u8,
+}
+
+impl EncodeOptions {
+    /// Default options, used by [`Qoi::encode`].
+    pub const DEFAULT: Self = EncodeOptions {
+        index_single_run: true,
+        index_lookup: true,
+        max_run: 62,
+    };
+
+    /// Options producing exactly the same output as the reference encoder.
+    pub const REFERENCE: Self = EncodeOptions {
+        index_single_run: false,
+        ..Self::DEFAULT
+    };
+}
+
+impl Default for EncodeOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[inline]
+fn blend(c: u8, bg: u8, a: u8) -> u8 {
+    ((c as u32 * a as u32 + bg as u32 * (255 - a as u32) + 127) / 255) as u8
+}
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels are written into `output` slice according to `options`.\
+    /// Use [`DecodeOptions::decoded_size`] to find required `output` size.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_with_options(
+        bytes: &[u8],
+        output: &mut [u8],
+        options: &DecodeOptions,
+    ) -> Result<Self, DecodeError> {
+        let (qoi, _) = Self::decode_with_options_consumed(bytes, output, options)?;
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels are written into `output` slice according to `options`.\
+    /// Allows callers embedding QOI images into larger files to find where image ends
+    /// and detect framing errors.
+    ///
+    /// On success this function returns `Ok((qoi, consumed))` with `qoi` describing image dimensions and color space
+    /// and `consumed` number of bytes taken by header, ops and end marker, if present.
+    /// In strict mode `consumed` is always equal to `bytes.len()`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_with_options_consumed(
+        bytes: &[u8],
+        output: &mut [u8],
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let size = match options.checked_decoded_size(&qoi) {
+            None => return Err(DecodeError::TooLarge),
+            Some(size) => size,
+        };
+
+        let output = match output.get_mut(..size) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let total = bytes.len();
+        let mut bytes = &bytes[QOI_HEADER_SIZE..];
+        let channels = options.channels(&qoi);
+        let order = options.order;
+
+        if channels == qoi.colors.channels()
+            && order == ChannelOrder::Rgba
+            && !(qoi.colors.has_alpha() && (options.matte.is_some() || options.premultiply))
+        {
+            qoi.decode_skip_header_from(&mut bytes, output)?;
+        } else {
+            // Dispatch once, so that reordering is resolved at compile time
+            // for each combination of output channels and order.
+            match (channels, order) {
+                (3, ChannelOrder::Rgba | ChannelOrder::Argb) => {
+                    qoi.decode_swizzled(&mut bytes, output, options, |[r, g, b, _]| [r, g, b])?
+                }
+                (3, ChannelOrder::Bgra | ChannelOrder::Abgr) => {
+                    qoi.decode_swizzled(&mut bytes, output, options, |[r, g, b, _]| [b, g, r])?
+                }
+                (_, ChannelOrder::Rgba) => {
+                    qoi.decode_swizzled(&mut bytes, output, options, |px| px)?
+                }
+                (_, ChannelOrder::Bgra) => {
+                    qoi.decode_swizzled(&mut bytes, output, options, |[r, g, b, a]| [b, g, r, a])?
+                }
+                (_, ChannelOrder::Argb) => {
+                    qoi.decode_swizzled(&mut bytes, output, options, |[r, g, b, a]| [a, r, g, b])?
+                }
+                (_, ChannelOrder::Abgr) => {
+                    qoi.decode_swizzled(&mut bytes, output, options, |[r, g, b, a]| [a, b, g, r])?
+                }
+            }
+        }
+
+        let mut consumed = total - bytes.len();
+        match Self::check_end_marker(bytes) {
+            Ok(()) => consumed += QOI_PADDING,
+            Err(err) if options.strict => return Err(err),
+            Err(_) => {}
+        }
+
+        if options.strict && consumed < total {
+            return Err(DecodeError::TrailingData);
+        }
+
+        Ok((qoi, consumed))
+    }
+
+    /// Decode all pixels of the image writing each one through `swizzle`.\
+    /// Pixels of images without alpha channel have alpha set to `255` before `swizzle`.
+    /// Pixels are blended over matte or premultiplied according to `options`.
+    fn decode_swizzled<const M: usize>(
+        &self,
+        bytes: &mut &[u8],
+        output: &mut [u8],
+        options: &DecodeOptions,
+        swizzle: impl Fn([u8; 4]) -> [u8; M],
+    ) -> Result<(), DecodeError> {
+        let mut output = output.chunks_exact_mut(M);
+
+        match self.colors.has_alpha() {
+            true => self.decode_chunks_from::<4>(bytes, |pixels| {
+                for (&[r, g, b, a], out) in pixels.iter().zip(output.by_ref()) {
+                    let px = match options.matte {
+                        Some(matte) => [
+                            blend(r, matte.r, a),
+                            blend(g, matte.g, a),
+                            blend(b, matte.b, a),
+                            255,
+                        ],
+                        None if options.premultiply => {
+                            [blend(r, 0, a), blend(g, 0, a), blend(b, 0, a), a]
+                        }
+                        None => [r, g, b, a],
+                    };
+                    out.copy_from_slice(&swizzle(px));
+                }
+            }),
+            false => self.decode_chunks_from::<3>(bytes, |pixels| {
+                for (&[r, g, b], out) in pixels.iter().zip(output.by_ref()) {
+                    out.copy_from_slice(&swizzle([r, g, b, 255]));
+                }
+            }),
+        }
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels are written into `output` slice with specified number of channels,
+    /// regardless of number of channels in the image.
+    /// Alpha is set to `255` when expanding RGB images and discarded when decoding RGBA images into RGB.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_with_channels(
+        bytes: &[u8],
+        output: &mut [u8],
+        channels: Channels,
+    ) -> Result<Self, DecodeError> {
+        let options = DecodeOptions {
+            expand_alpha: channels == Channels::Rgba,
+            drop_alpha: channels == Channels::Rgb,
+            ..DecodeOptions::default()
+        };
+
+        Self::decode_with_options(bytes, output, &options)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image with encoder heuristics controlled by `options`.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_with_options(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        options: &EncodeOptions,
+    ) -> Result<usize, EncodeError> {
+        let mut sink = SliceSink::new(output);
+        self.write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)?;
+        self.encode_to_sink(pixels, &mut sink, options)?;
+        Ok(sink.written())
+    }
+
+    /// Encode raw pixels with specified order of channels into a QOI image.\
+    /// Channels are reordered as pixels are read, without staging buffer.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_with_order(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        order: ChannelOrder,
+    ) -> Result<usize, EncodeError> {
+        // Channel `i` of encoded pixel is taken from channel `map[i]` of the source pixel.
+        let map = match (self.colors.has_alpha(), order) {
+            (false, ChannelOrder::Rgba | ChannelOrder::Argb) | (true, ChannelOrder::Rgba) => {
+                return self.encode(pixels, output)
+            }
+            (false, ChannelOrder::Bgra | ChannelOrder::Abgr) | (true, ChannelOrder::Bgra) => {
+                [2, 1, 0, 3]
+            }
+            (true, ChannelOrder::Argb) => [1, 2, 3, 0],
+            (true, ChannelOrder::Abgr) => [3, 2, 1, 0],
+        };
+
+        self.encode_with_pre_ops(pixels, output, &PreOps::new().swizzle(map))
+    }
+}
@@ -0,0 +1,204 @@
+use super::*;
+
+use crate::bytes::SliceSink;
+
+/// Order of channels in a pixel packed into single `u32` value.\
+/// Variants are named after channels from the most significant byte to the least significant one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PackedLayout {
+    /// `0xRRGGBBAA`.
+    Rgba,
+
+    /// `0xAARRGGBB`.\
+    /// Native layout of most software framebuffers.
+    Argb,
+
+    /// `0xAABBGGRR`.\
+    /// Same as RGBA bytes in memory on little-endian targets.
+    Abgr,
+
+    /// `0xBBGGRRAA`.
+    Bgra,
+}
+
+impl PackedLayout {
+    /// Packs pixel channels into `u32` value.
+    #[inline]
+    pub const fn pack(&self, [r, g, b, a]: [u8; 4]) -> u32 {
+        match self {
+            PackedLayout::Rgba => u32::from_be_bytes([r, g, b, a]),
+            PackedLayout::Argb => u32::from_be_bytes([a, r, g, b]),
+            PackedLayout::Abgr => u32::from_be_bytes([a, b, g, r]),
+            PackedLayout::Bgra => u32::from_be_bytes([b, g, r, a]),
+        }
+    }
+
+    /// Unpacks pixel channels from `u32` value.
+    #[inline]
+    pub const fn unpack(&self, v: u32) -> [u8; 4] {
+        let [x, y, z, w] = v.to_be_bytes();
+        match self {
+            PackedLayout::Rgba => [x, y, z, w],
+            PackedLayout::Argb => [y, z, w, x],
+            PackedLayout::Abgr => [w, z, y, x],
+            PackedLayout::Bgra => [z, y, x, w],
+        }
+    }
+}
+
+/// Layout of a pixel packed into single `u16` value.\
+/// Channels are reduced to fewer bits with rounding to the nearest value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Packed16Layout {
+    /// 5 bits of red in the most significant bits, 6 bits of green and 5 bits of blue.\
+    /// Alpha channel is discarded.\
+    /// Native layout of many embedded TFT displays.
+    Rgb565,
+
+    /// 4 bits of each channel, red in the most significant bits and alpha in the least significant ones.
+    Rgba4444,
+
+    /// 5 bits of each color channel with red in the most significant bits
+    /// and 1 bit of alpha in the least significant bit.
+    Rgba5551,
+}
+
+impl Packed16Layout {
+    /// Packs pixel channels into `u16` value.
+    #[inline]
+    pub const fn pack(&self, [r, g, b, a]: [u8; 4]) -> u16 {
+        let v = match self {
+            Packed16Layout::Rgb565 => reduce(r, 5) << 11 | reduce(g, 6) << 5 | reduce(b, 5),
+            Packed16Layout::Rgba4444 => {
+                reduce(r, 4) << 12 | reduce(g, 4) << 8 | reduce(b, 4) << 4 | reduce(a, 4)
+            }
+            Packed16Layout::Rgba5551 => {
+                reduce(r, 5) << 11 | reduce(g, 5) << 6 | reduce(b, 5) << 1 | reduce(a, 1)
+            }
+        };
+        v as u16
+    }
+}
+
+/// Reduces channel value to specified number of bits with rounding.
+#[inline]
+const fn reduce(c: u8, bits: u32) -> u32 {
+    let max = (1 << bits) - 1;
+    (c as u32 * max + 127) / 255
+}
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels are packed into `u32` values according to `layout` and written into `output` slice.\
+    /// Alpha channel of images without one is set to `255`.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_u32(
+        bytes: &[u8],
+        layout: PackedLayout,
+        output: &mut [u32],
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let px_count = qoi.pixel_count()?;
+        let output = match output.get_mut(..px_count) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+        let mut output = output.iter_mut();
+
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_chunks::<4>(bytes, |pixels| {
+                for (px, out) in pixels.iter().zip(output.by_ref()) {
+                    *out = layout.pack(*px);
+                }
+            })?,
+            false => qoi.decode_chunks::<3>(bytes, |pixels| {
+                for (px, out) in pixels.iter().zip(output.by_ref()) {
+                    *out = layout.pack(px.rgba());
+                }
+            })?,
+        }
+
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels are packed into `u16` values according to `layout` and written into `output` slice.\
+    /// Alpha channel of images without one is set to maximum value.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_u16(
+        bytes: &[u8],
+        layout: Packed16Layout,
+        output: &mut [u16],
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let px_count = qoi.pixel_count()?;
+        let output = match output.get_mut(..px_count) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+        let mut output = output.iter_mut();
+
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_chunks::<4>(bytes, |pixels| {
+                for (px, out) in pixels.iter().zip(output.by_ref()) {
+                    *out = layout.pack(*px);
+                }
+            })?,
+            false => qoi.decode_chunks::<3>(bytes, |pixels| {
+                for (px, out) in pixels.iter().zip(output.by_ref()) {
+                    *out = layout.pack(px.rgba());
+                }
+            })?,
+        }
+
+        Ok(qoi)
+    }
+
+    /// Encode pixels packed into `u32` values according to `layout` into a QOI image.\
+    /// Alpha channel is ignored if color space has no alpha.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_u32(
+        &self,
+        pixels: &[u32],
+        layout: PackedLayout,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let pixels = self.image_pixels(pixels, 1)?;
+
+        let mut sink = SliceSink::new(output);
+        self.write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)?;
+
+        let mut source = pixels.iter();
+
+        match self.colors.has_alpha() {
+            true => self.encode_chunks::<4>(&mut sink, |chunk| {
+                for (px, src) in chunk.iter_mut().zip(source.by_ref()) {
+                    *px = layout.unpack(*src);
+                }
+                Ok(())
+            })?,
+            false => self.encode_chunks::<3>(&mut sink, |chunk| {
+                for (px, src) in chunk.iter_mut().zip(source.by_ref()) {
+                    px.copy_from_slice(&layout.unpack(*src)[..3]);
+                }
+                Ok(())
+            })?,
+        }
+
+        Ok(sink.written())
+    }
+}
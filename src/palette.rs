@@ -0,0 +1,101 @@
+//! Palette extraction.
+//!
+//! Collects the most frequent colors of QOI image
+//! and writes them as GIMP (`.gpl`) or Photoshop (`.act`) palette.
+
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+
+use core::fmt::Write;
+
+use super::*;
+
+/// Maximum number of colors in Photoshop palette.
+pub const ACT_MAX_COLORS: usize = 256;
+
+/// Palette color with number of pixels that have this color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PaletteEntry {
+    /// Color value.
+    pub rgb: [u8; 3],
+
+    /// Number of pixels with this color.
+    pub count: u64,
+}
+
+/// Extracts up to `max_colors` most frequent colors from encoded QOI image.\
+/// Colors are weighted by number of pixels, so runs count as many times as they repeat.
+/// Alpha channel is ignored.
+///
+/// Returned entries are sorted from the most frequent color to the least frequent one.
+pub fn extract(bytes: &[u8], max_colors: usize) -> Result<Vec<PaletteEntry>, DecodeError> {
+    let qoi = Qoi::decode_header(bytes)?;
+    let bytes = &bytes[QOI_HEADER_SIZE..];
+
+    let mut counts = BTreeMap::new();
+    let mut last = None;
+    let mut count = 0;
+
+    let mut add = |rgb: [u8; 3]| {
+        if last == Some(rgb) {
+            count += 1;
+        } else {
+            if let Some(last) = last {
+                *counts.entry(last).or_insert(0) += count;
+            }
+            last = Some(rgb);
+            count = 1;
+        }
+    };
+
+    match qoi.colors.has_alpha() {
+        true => {
+            qoi.decode_chunks::<4>(bytes, |pixels| pixels.iter().for_each(|px| add(px.rgb())))?
+        }
+        false => qoi.decode_chunks::<3>(bytes, |pixels| pixels.iter().for_each(|px| add(*px)))?,
+    }
+
+    if let Some(last) = last {
+        *counts.entry(last).or_insert(0) += count;
+    }
+
+    let mut entries = counts
+        .into_iter()
+        .map(|(rgb, count)| PaletteEntry { rgb, count })
+        .collect::<Vec<_>>();
+
+    entries.sort_by(|a, b| b.count.cmp(&a.count).then(a.rgb.cmp(&b.rgb)));
+    entries.truncate(max_colors);
+    Ok(entries)
+}
+
+/// Formats palette as GIMP palette file.
+pub fn to_gpl(name: &str, palette: &[PaletteEntry]) -> String {
+    let mut gpl = String::new();
+    let _ = writeln!(gpl, "GIMP Palette");
+    let _ = writeln!(gpl, "Name: {}", name);
+    let _ = writeln!(gpl, "Columns: 16");
+    let _ = writeln!(gpl, "#");
+
+    for entry in palette {
+        let [r, g, b] = entry.rgb;
+        let _ = writeln!(gpl, "{:3} {:3} {:3}\t#{:02x}{:02x}{:02x}", r, g, b, r, g, b);
+    }
+    gpl
+}
+
+/// Formats palette as Photoshop color table file.\
+/// Only first [`ACT_MAX_COLORS`] colors are written.
+pub fn to_act(palette: &[PaletteEntry]) -> Vec<u8> {
+    let palette = &palette[..palette.len().min(ACT_MAX_COLORS)];
+
+    let mut act = vec![0; ACT_MAX_COLORS * 3 + 4];
+    for (entry, rgb) in palette.iter().zip(act.chunks_exact_mut(3)) {
+        rgb.copy_from_slice(&entry.rgb);
+    }
+
+    let tail = ACT_MAX_COLORS * 3;
+    act[tail..tail + 2].copy_from_slice(&(palette.len() as u16).to_be_bytes());
+    // No transparent color.
+    act[tail + 2..].copy_from_slice(&0xffffu16.to_be_bytes());
+    act
+}
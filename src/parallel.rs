@@ -0,0 +1,470 @@
+use super::*;
+
+use alloc::{vec, vec::Vec};
+use std::{num::NonZeroUsize, sync::Mutex, thread};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::bytes::{ByteSink, SliceSink, VecSink};
+
+/// Approximate number of pixels in a band encoded by single task.\
+/// Bands are made of whole rows and do not depend on number of threads,
+/// so encoded output is the same on every machine.
+const BAND_PIXELS: usize = 1 << 16;
+
+/// Initial index for bands after the first one.\
+/// Entry at every position has hash different from the position,
+/// so no pixel can hit an entry that was not written by the band itself.
+/// Entries written by the band are the same in decoder's index.
+const BAND_INDEX: [[u8; 4]; 64] = {
+    let mut index = [[0; 4]; 64];
+    index[0] = [1, 0, 0, 0];
+    index
+};
+
+/// Start of a band in image encoded by [`Qoi::encode_threaded_alloc_indexed`] or `Qoi::par_encode_alloc_indexed`.\
+/// Band index is a side-channel that allows [`Qoi::decode_threaded`] and `Qoi::decode_parallel`
+/// to decode bands concurrently.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BandOffset {
+    /// Offset of the first op of the band in encoded image, including header.
+    pub byte: usize,
+
+    /// Index of the first pixel of the band.
+    pub pixel: usize,
+}
+
+/// Runs band tasks concurrently.
+#[derive(Clone, Copy, Debug)]
+enum Threads {
+    /// Tasks run on rayon thread pool.
+    #[cfg(feature = "rayon")]
+    Rayon,
+
+    /// Tasks run on specified number of scoped threads.
+    Scoped(NonZeroUsize),
+}
+
+impl Threads {
+    /// Returns scoped threads mode.\
+    /// `0` threads is replaced with available parallelism.
+    fn scoped(threads: usize) -> Self {
+        let threads = NonZeroUsize::new(threads)
+            .or_else(|| thread::available_parallelism().ok())
+            .unwrap_or(NonZeroUsize::MIN);
+
+        Threads::Scoped(threads)
+    }
+
+    /// Applies `f` to every task and returns results in order of tasks.
+    fn map<T, R>(self, tasks: Vec<T>, f: impl Fn(T) -> R + Sync) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+    {
+        match self {
+            #[cfg(feature = "rayon")]
+            Threads::Rayon => tasks.into_par_iter().map(&f).collect(),
+            Threads::Scoped(threads) => {
+                let threads = threads.get().min(tasks.len());
+                if threads <= 1 {
+                    return tasks.into_iter().map(f).collect();
+                }
+
+                // Threads take tasks one by one, so bands that are slow to encode
+                // do not leave other threads idle.
+                let queue = Mutex::new(tasks.into_iter().enumerate());
+                let next = || queue.lock().ok().and_then(|mut queue| queue.next());
+                let (f, next) = (&f, &next);
+
+                let mut results = thread::scope(|scope| {
+                    let workers = (0..threads)
+                        .map(|_| {
+                            scope.spawn(move || {
+                                let mut results = Vec::new();
+                                while let Some((i, task)) = next() {
+                                    results.push((i, f(task)));
+                                }
+                                results
+                            })
+                        })
+                        .collect::<Vec<_>>();
+
+                    workers
+                        .into_iter()
+                        .flat_map(|worker| match worker.join() {
+                            Ok(results) => results,
+                            Err(panic) => std::panic::resume_unwind(panic),
+                        })
+                        .collect::<Vec<_>>()
+                });
+
+                results.sort_unstable_by_key(|&(i, _)| i);
+                results.into_iter().map(|(_, result)| result).collect()
+            }
+        }
+    }
+}
+
+impl Qoi {
+    /// Encode raw RGB or RGBA pixels into a QOI image using multiple threads.\
+    /// Image is split into horizontal bands encoded in parallel.
+    /// Each band but the first starts with explicit RGB or RGBA op
+    /// and never refers to pixels of previous bands, so stitched image is valid QOI.\
+    /// Output is slightly larger than produced by [`Qoi::encode`].\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn par_encode(&self, pixels: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+        self.encode_bands_into(pixels, output, Threads::Rayon)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image using multiple threads.\
+    /// See [`Qoi::par_encode`].\
+    /// Encoded image is written into allocated `Vec`.
+    ///
+    /// On success this function returns `Ok(vec)` with `vec` containing encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn par_encode_alloc(&self, pixels: &[u8]) -> Result<Vec<u8>, EncodeError> {
+        let (bytes, _) = self.par_encode_alloc_indexed(pixels)?;
+        Ok(bytes)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image using multiple threads.\
+    /// See [`Qoi::par_encode`].\
+    /// Encoded image is written into allocated `Vec`
+    /// and offsets of all bands are returned alongside for [`Qoi::decode_parallel`].
+    ///
+    /// On success this function returns `Ok((vec, index))` with `vec` containing encoded image and `index` with band offsets.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn par_encode_alloc_indexed(
+        &self,
+        pixels: &[u8],
+    ) -> Result<(Vec<u8>, Vec<BandOffset>), EncodeError> {
+        self.encode_bands_alloc(pixels, Threads::Rayon)
+    }
+
+    /// Decode a QOI image from bytes slice using multiple threads.\
+    /// `index` must contain offsets of all bands of the image,
+    /// as returned by [`Qoi::par_encode_alloc_indexed`].\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice.
+    ///
+    /// Fails with [`DecodeError::InvalidBandIndex`] if `index` does not match the image.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn decode_parallel(
+        bytes: &[u8],
+        index: &[BandOffset],
+        output: &mut [u8],
+    ) -> Result<Self, DecodeError> {
+        Self::decode_bands(bytes, index, output, Threads::Rayon)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image using `threads` scoped threads.\
+    /// Same as [`Qoi::par_encode`] without rayon dependency.
+    /// `0` threads uses number reported by [`std::thread::available_parallelism`].\
+    /// Image is split into the same bands regardless of number of threads,
+    /// so encoded image does not depend on it.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_threaded(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        threads: usize,
+    ) -> Result<usize, EncodeError> {
+        self.encode_bands_into(pixels, output, Threads::scoped(threads))
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image using `threads` scoped threads.\
+    /// See [`Qoi::encode_threaded`].\
+    /// Encoded image is written into allocated `Vec`.
+    ///
+    /// On success this function returns `Ok(vec)` with `vec` containing encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_threaded_alloc(
+        &self,
+        pixels: &[u8],
+        threads: usize,
+    ) -> Result<Vec<u8>, EncodeError> {
+        let (bytes, _) = self.encode_threaded_alloc_indexed(pixels, threads)?;
+        Ok(bytes)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image using `threads` scoped threads.\
+    /// See [`Qoi::encode_threaded`].\
+    /// Encoded image is written into allocated `Vec`
+    /// and offsets of all bands are returned alongside for [`Qoi::decode_threaded`].
+    ///
+    /// On success this function returns `Ok((vec, index))` with `vec` containing encoded image and `index` with band offsets.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_threaded_alloc_indexed(
+        &self,
+        pixels: &[u8],
+        threads: usize,
+    ) -> Result<(Vec<u8>, Vec<BandOffset>), EncodeError> {
+        self.encode_bands_alloc(pixels, Threads::scoped(threads))
+    }
+
+    /// Decode a QOI image from bytes slice using `threads` scoped threads.\
+    /// Same as `Qoi::decode_parallel` without rayon dependency.
+    /// `0` threads uses number reported by [`std::thread::available_parallelism`].\
+    /// `index` must contain offsets of all bands of the image,
+    /// as returned by [`Qoi::encode_threaded_alloc_indexed`].\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice.
+    ///
+    /// Fails with [`DecodeError::InvalidBandIndex`] if `index` does not match the image.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_threaded(
+        bytes: &[u8],
+        index: &[BandOffset],
+        output: &mut [u8],
+        threads: usize,
+    ) -> Result<Self, DecodeError> {
+        Self::decode_bands(bytes, index, output, Threads::scoped(threads))
+    }
+
+    /// Encode bands of the image concurrently into `output` slice.
+    fn encode_bands_into(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        threads: Threads,
+    ) -> Result<usize, EncodeError> {
+        let bands = self.encode_bands(pixels, threads)?;
+
+        let mut sink = SliceSink::new(output);
+        self.write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)?;
+        for band in &bands {
+            sink.write_bytes(band)?;
+        }
+        Self::write_padding_to_sink(&mut sink)?;
+
+        Ok(sink.written())
+    }
+
+    /// Encode bands of the image concurrently into allocated `Vec` and collect band offsets.
+    fn encode_bands_alloc(
+        &self,
+        pixels: &[u8],
+        threads: Threads,
+    ) -> Result<(Vec<u8>, Vec<BandOffset>), EncodeError> {
+        let bands = self.encode_bands(pixels, threads)?;
+        let band_pixels = self.band_rows() * self.width as usize;
+
+        let size = bands.iter().map(Vec::len).sum::<usize>() + QOI_HEADER_SIZE + QOI_PADDING;
+        let mut sink = VecSink::with_capacity(size);
+        let mut index = Vec::with_capacity(bands.len());
+
+        let result = self
+            .write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)
+            .and_then(|()| {
+                let mut offset = BandOffset {
+                    byte: QOI_HEADER_SIZE,
+                    pixel: 0,
+                };
+
+                for band in &bands {
+                    index.push(offset);
+                    sink.write_bytes(band)?;
+                    offset.byte += band.len();
+                    offset.pixel += band_pixels;
+                }
+
+                Self::write_padding_to_sink(&mut sink)
+            });
+
+        result.map(|()| (sink.into_vec(), index))
+    }
+
+    /// Decode bands of the image concurrently using band offsets from `index`.
+    fn decode_bands(
+        bytes: &[u8],
+        index: &[BandOffset],
+        output: &mut [u8],
+        threads: Threads,
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let channels = qoi.colors.channels();
+        let px_count = qoi.pixel_count()?;
+
+        let size = match qoi.checked_decoded_size() {
+            None => return Err(DecodeError::TooLarge),
+            Some(size) => size,
+        };
+
+        let mut output = match output.get_mut(..size) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        if px_count == 0 {
+            return Ok(qoi);
+        }
+
+        let ops_end = match bytes.len().checked_sub(QOI_PADDING) {
+            None => return Err(DecodeError::NotEnoughData),
+            Some(ops_end) => ops_end,
+        };
+
+        match index.first() {
+            Some(first) if first.byte == QOI_HEADER_SIZE && first.pixel == 0 => {}
+            _ => return Err(DecodeError::InvalidBandIndex),
+        }
+
+        // Split encoded bytes and output into bands.
+        let mut bands = Vec::with_capacity(index.len());
+        for (i, start) in index.iter().enumerate() {
+            let end = match index.get(i + 1) {
+                None => BandOffset {
+                    byte: ops_end,
+                    pixel: px_count,
+                },
+                Some(end) => *end,
+            };
+
+            if end.byte <= start.byte || end.pixel <= start.pixel || end.byte > ops_end {
+                return Err(DecodeError::InvalidBandIndex);
+            }
+
+            let len = match (end.pixel - start.pixel).checked_mul(channels) {
+                Some(len) if len <= output.len() => len,
+                _ => return Err(DecodeError::InvalidBandIndex),
+            };
+
+            let (band, rest) = core::mem::take(&mut output).split_at_mut(len);
+            output = rest;
+            bands.push((&bytes[start.byte..end.byte], band));
+        }
+
+        threads
+            .map(bands, |(source, band)| match qoi.colors.has_alpha() {
+                true => decode_band::<4>(source, band),
+                false => decode_band::<3>(source, band),
+            })
+            .into_iter()
+            .collect::<Result<(), _>>()?;
+
+        Ok(qoi)
+    }
+
+    /// Encode bands of the image concurrently.
+    fn encode_bands(&self, pixels: &[u8], threads: Threads) -> Result<Vec<Vec<u8>>, EncodeError> {
+        let channels = self.colors.channels();
+        let pixels = self.image_pixels(pixels, channels)?;
+
+        if pixels.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let band_len = match self.band_rows().checked_mul(self.width as usize * channels) {
+            None => return Err(EncodeError::TooLarge),
+            Some(band_len) => band_len,
+        };
+
+        let bands = pixels.chunks(band_len).enumerate().collect();
+
+        threads
+            .map(bands, |(i, band)| match self.colors.has_alpha() {
+                true => encode_band::<4>(band, i == 0),
+                false => encode_band::<3>(band, i == 0),
+            })
+            .into_iter()
+            .collect()
+    }
+
+    /// Returns number of rows in a band.
+    fn band_rows(&self) -> usize {
+        match self.width {
+            0 => 1,
+            width => (BAND_PIXELS / width as usize).max(1),
+        }
+    }
+}
+
+/// Decode band of pixels from clean state.\
+/// Fails if `source` is not consumed exactly.
+fn decode_band<const N: usize>(mut source: &[u8], band: &mut [u8]) -> Result<(), DecodeError>
+where
+    [u8; N]: Pixel,
+{
+    let mut run = 0;
+
+    Qoi::decode_range_from_source::<N>(
+        &mut [Pixel::new(); 64],
+        &mut Pixel::new_opaque(),
+        &mut run,
+        &mut source,
+        band,
+    )?;
+
+    match (source.is_empty(), run) {
+        (true, 0) => Ok(()),
+        _ => Err(DecodeError::InvalidBandIndex),
+    }
+}
+
+/// Encode band of pixels with pending run flushed at the end.\
+/// Bands other than the first start from clean state with literal op.
+fn encode_band<const N: usize>(band: &[u8], first: bool) -> Result<Vec<u8>, EncodeError>
+where
+    [u8; N]: Pixel,
+{
+    let mut output = vec![0; band.len() / N * (N + 1) + QOI_PADDING];
+    let mut run = 0;
+
+    let (mut index, mut px_prev, size, band) = match first {
+        true => ([[0; 4]; 64], <[u8; N]>::new_opaque(), 0, band),
+        false => {
+            let mut index = BAND_INDEX;
+            let mut px = <[u8; N]>::new();
+            px.read(&band[..N]);
+
+            let [r, g, b, a] = px.rgba();
+            let size = match N {
+                4 => {
+                    output[..5].copy_from_slice(&[QOI_OP_RGBA, r, g, b, a]);
+                    5
+                }
+                _ => {
+                    output[..4].copy_from_slice(&[QOI_OP_RGB, r, g, b]);
+                    4
+                }
+            };
+
+            index[px.hash() as usize] = px.rgba();
+            (index, px, size, &band[N..])
+        }
+    };
+
+    let written = Qoi::encode_range_impl::<N>(
+        &mut index,
+        &mut px_prev,
+        &mut run,
+        band,
+        &mut output[size..],
+        true,
+    )?;
+
+    output.truncate(size + written);
+    Ok(output)
+}
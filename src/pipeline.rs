@@ -0,0 +1,325 @@
+//! Per-pixel conversions applied to decoded pixels and to pixels before encoding.
+//!
+//! [`PostOps`] fuses several conversions into a single pass over the output buffer.\
+//! [`PreOps`] applies conversions while encoder reads source pixels, without staging buffer.
+
+use super::*;
+
+use crate::bytes::SliceSink;
+
+/// Set of per-pixel conversions applied to decoded pixels in a single pass.\
+/// Regardless of the order in which conversions are added,
+/// they are applied in this order: sRGB to linear, premultiply, swizzle.
+///
+/// ```
+/// # use rapid_qoi::pipeline::PostOps;
+/// let ops = PostOps::new().srgb_to_linear().premultiply().swizzle([2, 1, 0, 3]);
+/// let mut pixels = [255, 128, 0, 128];
+/// ops.apply(&mut pixels, 4);
+/// assert_eq!(pixels, [0, 28, 128, 128]);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PostOps {
+    srgb_to_linear: bool,
+    premultiply: bool,
+    swizzle: Option<[u8; 4]>,
+}
+
+impl PostOps {
+    /// Returns pipeline without conversions.
+    #[inline]
+    pub const fn new() -> Self {
+        PostOps {
+            srgb_to_linear: false,
+            premultiply: false,
+            swizzle: None,
+        }
+    }
+
+    /// Converts color channels from sRGB to linear encoding.\
+    /// Alpha channel is not affected.
+    #[inline]
+    pub const fn srgb_to_linear(mut self) -> Self {
+        self.srgb_to_linear = true;
+        self
+    }
+
+    /// Multiplies color channels by alpha.\
+    /// Has no effect on pixels without alpha channel.
+    #[inline]
+    pub const fn premultiply(mut self) -> Self {
+        self.premultiply = true;
+        self
+    }
+
+    /// Reorders channels.
+    /// Channel `i` of the result is taken from channel `map[i]` of the source pixel.\
+    /// Only first three elements are used for pixels without alpha channel.
+    ///
+    /// Conversion panics if `map` references channel that pixel does not have.
+    #[inline]
+    pub const fn swizzle(mut self, map: [u8; 4]) -> Self {
+        self.swizzle = Some(map);
+        self
+    }
+
+    /// Applies conversions to every pixel in `pixels`.\
+    /// `channels` must be `3` or `4`.
+    pub fn apply(&self, pixels: &mut [u8], channels: usize) {
+        match channels {
+            3 => self.apply_n::<3>(pixels),
+            4 => self.apply_n::<4>(pixels),
+            _ => panic!("Unsupported number of channels"),
+        }
+    }
+
+    /// Applies conversions to decoded pixels of the image.\
+    /// Rows are processed in parallel.
+    #[cfg(feature = "rayon")]
+    pub fn par_apply(&self, qoi: &Qoi, pixels: &mut [u8]) {
+        use rayon::prelude::*;
+
+        let channels = qoi.colors.channels();
+        let row = qoi.width as usize * channels;
+        if row == 0 {
+            return;
+        }
+
+        pixels[..qoi.decoded_size()]
+            .par_chunks_mut(row)
+            .for_each(|row| self.apply(row, channels));
+    }
+
+    fn apply_n<const N: usize>(&self, pixels: &mut [u8]) {
+        assert_eq!(pixels.len() % N, 0);
+
+        for px in pixels.chunks_exact_mut(N) {
+            if self.srgb_to_linear {
+                for c in &mut px[..3] {
+                    *c = SRGB_TO_LINEAR[*c as usize];
+                }
+            }
+
+            if self.premultiply && N == 4 {
+                let a = px[3] as u32;
+                for c in &mut px[..3] {
+                    *c = ((*c as u32 * a + 127) / 255) as u8;
+                }
+            }
+
+            if let Some(map) = self.swizzle {
+                let mut src = [0; N];
+                src.copy_from_slice(px);
+                for (c, &i) in px.iter_mut().zip(&map) {
+                    *c = src[i as usize];
+                }
+            }
+        }
+    }
+}
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice and apply conversions to decoded pixels.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_with_post_ops(
+        bytes: &[u8],
+        output: &mut [u8],
+        ops: &PostOps,
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode(bytes, output)?;
+        ops.apply(&mut output[..qoi.decoded_size()], qoi.colors.channels());
+        Ok(qoi)
+    }
+}
+
+/// Set of per-pixel conversions applied to source pixels during encoding.\
+/// Mirrors [`PostOps`]. Regardless of the order in which conversions are added,
+/// they are applied in this order: swizzle, un-premultiply, linear to sRGB.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PreOps {
+    swizzle: Option<[u8; 4]>,
+    unpremultiply: bool,
+    linear_to_srgb: bool,
+}
+
+impl PreOps {
+    /// Returns pipeline without conversions.
+    #[inline]
+    pub const fn new() -> Self {
+        PreOps {
+            swizzle: None,
+            unpremultiply: false,
+            linear_to_srgb: false,
+        }
+    }
+
+    /// Reorders channels.
+    /// Channel `i` of the encoded pixel is taken from channel `map[i]` of the source pixel.\
+    /// Only first three elements are used for pixels without alpha channel.
+    ///
+    /// Conversion panics if `map` references channel that pixel does not have.
+    #[inline]
+    pub const fn swizzle(mut self, map: [u8; 4]) -> Self {
+        self.swizzle = Some(map);
+        self
+    }
+
+    /// Divides color channels by alpha, converting premultiplied pixels to straight alpha.\
+    /// Color channels of fully transparent pixels are set to zero.
+    /// Has no effect on pixels without alpha channel.
+    #[inline]
+    pub const fn unpremultiply(mut self) -> Self {
+        self.unpremultiply = true;
+        self
+    }
+
+    /// Converts color channels from linear to sRGB encoding.\
+    /// Alpha channel is not affected.
+    #[inline]
+    pub const fn linear_to_srgb(mut self) -> Self {
+        self.linear_to_srgb = true;
+        self
+    }
+
+    /// Applies conversions to every pixel in `pixels`.\
+    /// `channels` must be `3` or `4`.
+    pub fn apply(&self, pixels: &mut [u8], channels: usize) {
+        match channels {
+            3 => pixels
+                .chunks_exact_mut(3)
+                .for_each(|px| self.apply_px::<3>(px)),
+            4 => pixels
+                .chunks_exact_mut(4)
+                .for_each(|px| self.apply_px::<4>(px)),
+            _ => panic!("Unsupported number of channels"),
+        }
+    }
+
+    #[inline]
+    fn apply_px<const N: usize>(&self, px: &mut [u8]) {
+        if let Some(map) = self.swizzle {
+            let mut src = [0; N];
+            src.copy_from_slice(px);
+            for (c, &i) in px.iter_mut().zip(&map) {
+                *c = src[i as usize];
+            }
+        }
+
+        if self.unpremultiply && N == 4 {
+            let a = px[3] as u32;
+            for c in &mut px[..3] {
+                *c = match a {
+                    0 => 0,
+                    _ => ((*c as u32 * 255 + a / 2) / a).min(255) as u8,
+                };
+            }
+        }
+
+        if self.linear_to_srgb {
+            for c in &mut px[..3] {
+                *c = LINEAR_TO_SRGB[*c as usize];
+            }
+        }
+    }
+}
+
+impl Qoi {
+    /// Encode raw RGB or RGBA pixels into a QOI image applying conversions to each pixel as it is read.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_with_pre_ops(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        ops: &PreOps,
+    ) -> Result<usize, EncodeError> {
+        let channels = self.colors.channels();
+        let pixels = self.image_pixels(pixels, channels)?;
+
+        let mut sink = SliceSink::new(output);
+        self.write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)?;
+
+        let mut source = pixels.chunks_exact(channels);
+
+        match self.colors.has_alpha() {
+            true => self.encode_chunks::<4>(&mut sink, |chunk| {
+                for (px, src) in chunk.iter_mut().zip(source.by_ref()) {
+                    px.copy_from_slice(src);
+                    ops.apply_px::<4>(px);
+                }
+                Ok(())
+            })?,
+            false => self.encode_chunks::<3>(&mut sink, |chunk| {
+                for (px, src) in chunk.iter_mut().zip(source.by_ref()) {
+                    px.copy_from_slice(src);
+                    ops.apply_px::<3>(px);
+                }
+                Ok(())
+            })?,
+        }
+
+        Ok(sink.written())
+    }
+
+    /// Encode raw RGBA pixels with premultiplied alpha into a QOI image.\
+    /// QOI stores straight alpha, so color channels are divided by alpha as pixels are read.
+    /// Same as [`Qoi::encode_with_pre_ops`] with [`PreOps::unpremultiply`].\
+    /// Pixels without alpha channel are encoded as is.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_premultiplied(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        self.encode_with_pre_ops(pixels, output, &PreOps::new().unpremultiply())
+    }
+}
+
+#[rustfmt::skip]
+const SRGB_TO_LINEAR: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3,
+    4, 4, 4, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7,
+    8, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 12, 12, 12, 13,
+    13, 13, 14, 14, 15, 15, 16, 16, 17, 17, 17, 18, 18, 19, 19, 20,
+    20, 21, 22, 22, 23, 23, 24, 24, 25, 25, 26, 27, 27, 28, 29, 29,
+    30, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37, 37, 38, 39, 40, 41,
+    41, 42, 43, 44, 45, 45, 46, 47, 48, 49, 50, 51, 51, 52, 53, 54,
+    55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70,
+    71, 72, 73, 74, 76, 77, 78, 79, 80, 81, 82, 84, 85, 86, 87, 88,
+    90, 91, 92, 93, 95, 96, 97, 99, 100, 101, 103, 104, 105, 107, 108, 109,
+    111, 112, 114, 115, 116, 118, 119, 121, 122, 124, 125, 127, 128, 130, 131, 133,
+    134, 136, 138, 139, 141, 142, 144, 146, 147, 149, 151, 152, 154, 156, 157, 159,
+    161, 163, 164, 166, 168, 170, 171, 173, 175, 177, 179, 181, 183, 184, 186, 188,
+    190, 192, 194, 196, 198, 200, 202, 204, 206, 208, 210, 212, 214, 216, 218, 220,
+    222, 224, 226, 229, 231, 233, 235, 237, 239, 242, 244, 246, 248, 250, 253, 255,
+];
+
+#[rustfmt::skip]
+const LINEAR_TO_SRGB: [u8; 256] = [
+    0, 13, 22, 28, 34, 38, 42, 46, 50, 53, 56, 59, 61, 64, 66, 69,
+    71, 73, 75, 77, 79, 81, 83, 85, 86, 88, 90, 92, 93, 95, 96, 98,
+    99, 101, 102, 104, 105, 106, 108, 109, 110, 112, 113, 114, 115, 117, 118, 119,
+    120, 121, 122, 124, 125, 126, 127, 128, 129, 130, 131, 132, 133, 134, 135, 136,
+    137, 138, 139, 140, 141, 142, 143, 144, 145, 146, 147, 148, 148, 149, 150, 151,
+    152, 153, 154, 155, 155, 156, 157, 158, 159, 159, 160, 161, 162, 163, 163, 164,
+    165, 166, 167, 167, 168, 169, 170, 170, 171, 172, 173, 173, 174, 175, 175, 176,
+    177, 178, 178, 179, 180, 180, 181, 182, 182, 183, 184, 185, 185, 186, 187, 187,
+    188, 189, 189, 190, 190, 191, 192, 192, 193, 194, 194, 195, 196, 196, 197, 197,
+    198, 199, 199, 200, 200, 201, 202, 202, 203, 203, 204, 205, 205, 206, 206, 207,
+    208, 208, 209, 209, 210, 210, 211, 212, 212, 213, 213, 214, 214, 215, 215, 216,
+    216, 217, 218, 218, 219, 219, 220, 220, 221, 221, 222, 222, 223, 223, 224, 224,
+    225, 226, 226, 227, 227, 228, 228, 229, 229, 230, 230, 231, 231, 232, 232, 233,
+    233, 234, 234, 235, 235, 236, 236, 237, 237, 238, 238, 238, 239, 239, 240, 240,
+    241, 241, 242, 242, 243, 243, 244, 244, 245, 245, 246, 246, 246, 247, 247, 248,
+    248, 249, 249, 250, 250, 251, 251, 251, 252, 252, 253, 253, 254, 254, 255, 255,
+];
@@ -0,0 +1,126 @@
+use super::*;
+
+use crate::bytes::SliceSink;
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice into separate channel planes.\
+    /// Each plane receives one byte per pixel, rows tightly packed.\
+    /// Alpha plane is optional, alpha of images without one is set to `255`.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_planar(
+        bytes: &[u8],
+        r: &mut [u8],
+        g: &mut [u8],
+        b: &mut [u8],
+        a: Option<&mut [u8]>,
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let px_count = qoi.pixel_count()?;
+
+        let too_small = |plane: &[u8]| plane.len() < px_count;
+        if too_small(r) || too_small(g) || too_small(b) || a.as_deref().is_some_and(too_small) {
+            return Err(DecodeError::OutputIsTooSmall);
+        }
+
+        let mut a = a;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+        let mut pos = 0;
+
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_chunks::<4>(bytes, |pixels| {
+                let range = pos..pos + pixels.len();
+                let r = &mut r[range.clone()];
+                let g = &mut g[range.clone()];
+                let b = &mut b[range.clone()];
+
+                for (i, &[pr, pg, pb, _]) in pixels.iter().enumerate() {
+                    r[i] = pr;
+                    g[i] = pg;
+                    b[i] = pb;
+                }
+
+                if let Some(a) = &mut a {
+                    for (a, px) in a[range].iter_mut().zip(pixels) {
+                        *a = px[3];
+                    }
+                }
+
+                pos += pixels.len();
+            })?,
+            false => {
+                qoi.decode_chunks::<3>(bytes, |pixels| {
+                    let range = pos..pos + pixels.len();
+                    let r = &mut r[range.clone()];
+                    let g = &mut g[range.clone()];
+                    let b = &mut b[range];
+
+                    for (i, &[pr, pg, pb]) in pixels.iter().enumerate() {
+                        r[i] = pr;
+                        g[i] = pg;
+                        b[i] = pb;
+                    }
+
+                    pos += pixels.len();
+                })?;
+
+                if let Some(a) = a {
+                    a[..px_count].fill(0xff);
+                }
+            }
+        }
+
+        Ok(qoi)
+    }
+
+    /// Encode pixels from separate channel planes into a QOI image.\
+    /// Each plane contains one byte per pixel, rows tightly packed.\
+    /// Alpha plane is optional, alpha is `255` if it is not provided.
+    /// Alpha is ignored if color space has no alpha.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_planar(
+        &self,
+        r: &[u8],
+        g: &[u8],
+        b: &[u8],
+        a: Option<&[u8]>,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let px_count = self.pixels_len(1)?;
+
+        let too_small = |plane: &[u8]| plane.len() < px_count;
+        if too_small(r) || too_small(g) || too_small(b) || a.is_some_and(too_small) {
+            return Err(EncodeError::NotEnoughPixelData);
+        }
+
+        let mut sink = SliceSink::new(output);
+        self.write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)?;
+
+        let mut pos = 0;
+
+        match self.colors.has_alpha() {
+            true => self.encode_chunks::<4>(&mut sink, |chunk| {
+                for (i, px) in chunk.iter_mut().enumerate() {
+                    let j = pos + i;
+                    *px = [r[j], g[j], b[j], a.map_or(0xff, |a| a[j])];
+                }
+                pos += chunk.len();
+                Ok(())
+            })?,
+            false => self.encode_chunks::<3>(&mut sink, |chunk| {
+                for (i, px) in chunk.iter_mut().enumerate() {
+                    let j = pos + i;
+                    *px = [r[j], g[j], b[j]];
+                }
+                pos += chunk.len();
+                Ok(())
+            })?,
+        }
+
+        Ok(sink.written())
+    }
+}
@@ -0,0 +1,132 @@
+use super::*;
+
+/// Rectangular region of the image in pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rect {
+    /// Left edge of the region.
+    pub x: u32,
+
+    /// Top edge of the region.
+    pub y: u32,
+
+    /// Width of the region.
+    pub width: u32,
+
+    /// Height of the region.
+    pub height: u32,
+}
+
+impl Qoi {
+    /// Decode a region of QOI image from bytes slice.\
+    /// Decoded raw RGB or RGBA pixels of the region are written into `output` slice
+    /// tightly packed with `rect.width` pixels per row.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing whole image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_region(bytes: &[u8], rect: Rect, output: &mut [u8]) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        qoi.decode_region_skip_header(&bytes[QOI_HEADER_SIZE..], rect, output)?;
+        Ok(qoi)
+    }
+
+    /// Decode a region of QOI image from bytes slice.\
+    /// `bytes` does not include QOI header. Uses provided `Qoi` value instead.\
+    /// Decoded raw RGB or RGBA pixels of the region are written into `output` slice
+    /// tightly packed with `rect.width` pixels per row.
+    ///
+    /// Decoding stops right after the last pixel of the region.\
+    /// `bytes` must be plain QOI data, filtered data of images with extended header is not supported.
+    ///
+    /// On success this function returns `Ok(())`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_region_skip_header(
+        &self,
+        bytes: &[u8],
+        rect: Rect,
+        output: &mut [u8],
+    ) -> Result<(), DecodeError> {
+        if rect.x as u64 + rect.width as u64 > self.width as u64
+            || rect.y as u64 + rect.height as u64 > self.height as u64
+        {
+            return Err(DecodeError::InvalidRegion);
+        }
+
+        if rect.width == 0 || rect.height == 0 {
+            return Ok(());
+        }
+
+        let region = Qoi {
+            width: rect.width,
+            height: rect.height,
+            colors: self.colors,
+        };
+
+        let px_len = match region.checked_decoded_size() {
+            None => return Err(DecodeError::TooLarge),
+            Some(px_len) => px_len,
+        };
+
+        let output = match output.get_mut(..px_len) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        match self.colors.has_alpha() {
+            true => self.decode_region_impl::<4>(bytes, rect, output),
+            false => self.decode_region_impl::<3>(bytes, rect, output),
+        }
+    }
+
+    fn decode_region_impl<const N: usize>(
+        &self,
+        bytes: &[u8],
+        rect: Rect,
+        output: &mut [u8],
+    ) -> Result<(), DecodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        const CHUNK: usize = 256;
+
+        let width = self.width as usize;
+        let left = rect.x as usize;
+        let right = left + rect.width as usize;
+        let top = rect.y as usize;
+        let end = (top + rect.height as usize - 1) * width + right;
+
+        let mut index = [Pixel::new(); 64];
+        let mut px = Pixel::new_opaque();
+        let mut run = 0;
+
+        let mut buffer = [[0; N]; CHUNK];
+        let mut pos = 0;
+        let mut source = bytes;
+
+        while pos < end {
+            let chunk = &mut buffer[..(end - pos).min(CHUNK)];
+            Self::decode_range_from_source::<N>(
+                &mut index,
+                &mut px,
+                &mut run,
+                &mut source,
+                chunk.as_flattened_mut(),
+            )?;
+
+            let chunk_end = pos + chunk.len();
+            for y in (pos / width).max(top)..=(chunk_end - 1) / width {
+                let start = (y * width + left).max(pos);
+                let stop = (y * width + right).min(chunk_end);
+                if start < stop {
+                    let offset = ((y - top) * rect.width as usize + start - y * width - left) * N;
+                    let pixels = chunk[start - pos..stop - pos].as_flattened();
+                    output[offset..][..pixels.len()].copy_from_slice(pixels);
+                }
+            }
+
+            pos = chunk_end;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use super::*;
+
+use ::rgb::{RGB8, RGBA8};
+
+impl From<RGB8> for Rgb {
+    #[inline]
+    fn from(RGB8 { r, g, b }: RGB8) -> Self {
+        Rgb { r, g, b }
+    }
+}
+
+impl From<Rgb> for RGB8 {
+    #[inline]
+    fn from(Rgb { r, g, b }: Rgb) -> Self {
+        RGB8 { r, g, b }
+    }
+}
+
+impl From<RGBA8> for Rgba {
+    #[inline]
+    fn from(RGBA8 { r, g, b, a }: RGBA8) -> Self {
+        Rgba { r, g, b, a }
+    }
+}
+
+impl From<Rgba> for RGBA8 {
+    #[inline]
+    fn from(Rgba { r, g, b, a }: Rgba) -> Self {
+        RGBA8 { r, g, b, a }
+    }
+}
+
+impl EncodePixel for RGB8 {
+    const HAS_ALPHA: bool = false;
+
+    #[inline]
+    fn rgba(&self) -> [u8; 4] {
+        [self.r, self.g, self.b, 255]
+    }
+}
+
+impl EncodePixel for RGBA8 {
+    const HAS_ALPHA: bool = true;
+
+    #[inline]
+    fn rgba(&self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels are written into `output` slice of [`RGB8`] values.\
+    /// Alpha channel of images with one is discarded.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_rgb8(bytes: &[u8], output: &mut [RGB8]) -> Result<Self, DecodeError> {
+        Self::decode_rgb_impl(bytes, output, |[r, g, b, _]| RGB8 { r, g, b })
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels are written into `output` slice of [`RGBA8`] values.\
+    /// Alpha channel of images without one is set to `255`.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_rgba8(bytes: &[u8], output: &mut [RGBA8]) -> Result<Self, DecodeError> {
+        Self::decode_rgb_impl(bytes, output, |[r, g, b, a]| RGBA8 { r, g, b, a })
+    }
+
+    fn decode_rgb_impl<P>(
+        bytes: &[u8],
+        output: &mut [P],
+        convert: impl Fn([u8; 4]) -> P,
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let px_count = qoi.pixel_count()?;
+        let output = match output.get_mut(..px_count) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+        let mut output = output.iter_mut();
+
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_chunks::<4>(bytes, |pixels| {
+                for (px, out) in pixels.iter().zip(output.by_ref()) {
+                    *out = convert(*px);
+                }
+            })?,
+            false => qoi.decode_chunks::<3>(bytes, |pixels| {
+                for (px, out) in pixels.iter().zip(output.by_ref()) {
+                    *out = convert(px.rgba());
+                }
+            })?,
+        }
+
+        Ok(qoi)
+    }
+}
@@ -0,0 +1,320 @@
+use super::*;
+
+#[cfg(feature = "alloc")]
+use alloc::vec;
+
+use crate::bytes::{ByteSink, SliceSink};
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice row by row.\
+    /// `f` is called with index and raw RGB or RGBA pixels of each row as soon as it is decoded.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    /// Rows decoded before the error are passed to `f`.
+    #[cfg(feature = "alloc")]
+    pub fn decode_rows(bytes: &[u8], f: impl FnMut(u32, &[u8])) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_rows_impl::<4>(bytes, f)?,
+            false => qoi.decode_rows_impl::<3>(bytes, f)?,
+        }
+
+        Ok(qoi)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn decode_rows_impl<const N: usize>(
+        &self,
+        bytes: &[u8],
+        mut f: impl FnMut(u32, &[u8]),
+    ) -> Result<(), DecodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        if self.width == 0 {
+            return Ok(());
+        }
+
+        let mut index = [Pixel::new(); 64];
+        let mut px = Pixel::new_opaque();
+        let mut run = 0;
+
+        let row_len = match (self.width as usize).checked_mul(N) {
+            None => return Err(DecodeError::TooLarge),
+            Some(row_len) => row_len,
+        };
+
+        let mut row = vec![0; row_len];
+        let mut source = bytes;
+
+        for y in 0..self.height {
+            Self::decode_range_from_source::<N>(
+                &mut index,
+                &mut px,
+                &mut run,
+                &mut source,
+                &mut row,
+            )?;
+            f(y, &row);
+        }
+
+        Ok(())
+    }
+
+    /// Decode a QOI image from bytes slice into `output` slice with rows `row_pitch` bytes apart.\
+    /// Decoded raw RGB or RGBA pixels of each row are written at the start of the row.
+    /// Padding bytes at the end of each row are left untouched.\
+    /// `row_pitch` must be at least `width * channels`, otherwise [`DecodeError::OutputIsTooSmall`] is returned.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_strided(
+        bytes: &[u8],
+        output: &mut [u8],
+        row_pitch: usize,
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        if qoi.width == 0 || qoi.height == 0 {
+            return Ok(qoi);
+        }
+
+        let row_len = match (qoi.width as usize).checked_mul(qoi.colors.channels()) {
+            None => return Err(DecodeError::TooLarge),
+            Some(row_len) => row_len,
+        };
+
+        if row_pitch < row_len {
+            return Err(DecodeError::OutputIsTooSmall);
+        }
+
+        let size = match (qoi.height as usize - 1)
+            .checked_mul(row_pitch)
+            .and_then(|size| size.checked_add(row_len))
+        {
+            None => return Err(DecodeError::TooLarge),
+            Some(size) => size,
+        };
+
+        let output = match output.get_mut(..size) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        match qoi.colors.has_alpha() {
+            true => Self::decode_strided_impl::<4>(bytes, output, row_len, row_pitch)?,
+            false => Self::decode_strided_impl::<3>(bytes, output, row_len, row_pitch)?,
+        }
+
+        Ok(qoi)
+    }
+
+    fn decode_strided_impl<const N: usize>(
+        bytes: &[u8],
+        output: &mut [u8],
+        row_len: usize,
+        row_pitch: usize,
+    ) -> Result<(), DecodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let mut index = [Pixel::new(); 64];
+        let mut px = Pixel::new_opaque();
+        let mut run = 0;
+
+        let mut source = bytes;
+
+        for row in output.chunks_mut(row_pitch) {
+            Self::decode_range_from_source::<N>(
+                &mut index,
+                &mut px,
+                &mut run,
+                &mut source,
+                &mut row[..row_len],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns row pitch of `width * channels` bytes rounded up to multiple of `alignment`.\
+    /// `alignment` of zero is treated as one.\
+    /// Fails with [`DecodeError::TooLarge`] if it does not fit into `usize`.
+    #[inline]
+    pub fn aligned_row_pitch(&self, alignment: usize) -> Result<usize, DecodeError> {
+        let alignment = alignment.max(1);
+        let row_len = (self.width as usize).checked_mul(self.colors.channels());
+
+        match row_len.and_then(|row_len| row_len.div_ceil(alignment).checked_mul(alignment)) {
+            None => Err(DecodeError::TooLarge),
+            Some(row_pitch) => Ok(row_pitch),
+        }
+    }
+
+    /// Returns bytes size of the output with rows aligned to `alignment` bytes.\
+    /// Fails with [`DecodeError::TooLarge`] if it does not fit into `usize`.\
+    /// See [`Qoi::decode_aligned`].
+    #[inline]
+    pub fn aligned_size(&self, alignment: usize) -> Result<usize, DecodeError> {
+        match self
+            .aligned_row_pitch(alignment)?
+            .checked_mul(self.height as usize)
+        {
+            None => Err(DecodeError::TooLarge),
+            Some(size) => Ok(size),
+        }
+    }
+
+    /// Decode a QOI image from bytes slice into `output` slice with each row starting
+    /// at multiple of `alignment` bytes, e.g. `256` for GPU texture staging buffers.\
+    /// Padding bytes at the end of each row are left untouched.\
+    /// Use [`Qoi::aligned_size`] to find required `output` size.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_aligned(
+        bytes: &[u8],
+        output: &mut [u8],
+        alignment: usize,
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        Self::decode_strided(bytes, output, qoi.aligned_row_pitch(alignment)?)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image pulling rows from `row` callback.\
+    /// `row` is called with index of each row in order and returns its pixels.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_rows<'a>(
+        &self,
+        output: &mut [u8],
+        row: impl FnMut(u32) -> &'a [u8],
+    ) -> Result<usize, EncodeError> {
+        let mut sink = SliceSink::new(output);
+        self.write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)?;
+
+        match self.colors.has_alpha() {
+            true => self.encode_rows_impl::<4>(&mut sink, row)?,
+            false => self.encode_rows_impl::<3>(&mut sink, row)?,
+        }
+
+        Self::write_padding_to_sink(&mut sink)?;
+        Ok(sink.written())
+    }
+
+    /// Encode raw RGB or RGBA pixels read from `pixels` slice with rows `row_pitch` bytes apart.\
+    /// Padding bytes at the end of each row are ignored.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_strided(
+        &self,
+        pixels: &[u8],
+        row_pitch: usize,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        self.encode_strided_at(pixels, row_pitch, 0, 0, output)
+    }
+
+    /// Encode raw RGB or RGBA pixels read from `pixels` slice with rows `row_pitch` bytes apart,
+    /// starting at pixel `x` of row `y`.\
+    /// Allows to encode part of a larger surface without copying.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// Fails with [`EncodeError::NotEnoughPixelData`] if rows of the image
+    /// do not fit into `row_pitch` or `pixels` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_strided_at(
+        &self,
+        pixels: &[u8],
+        row_pitch: usize,
+        x: u32,
+        y: u32,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let channels = self.colors.channels();
+
+        // Offsets that do not fit into `usize` are past the end of `pixels`.
+        let left = match (x as usize).checked_mul(channels) {
+            None => return Err(EncodeError::NotEnoughPixelData),
+            Some(left) => left,
+        };
+
+        let right = (self.width as usize)
+            .checked_mul(channels)
+            .and_then(|row_len| row_len.checked_add(left));
+
+        match right {
+            Some(right) if self.width == 0 || right <= row_pitch => {}
+            _ => return Err(EncodeError::NotEnoughPixelData),
+        }
+
+        let start = match (y as usize)
+            .checked_mul(row_pitch)
+            .and_then(|start| start.checked_add(left))
+        {
+            None => return Err(EncodeError::NotEnoughPixelData),
+            Some(start) => start,
+        };
+
+        self.encode_rows(output, |row| {
+            (row as usize)
+                .checked_mul(row_pitch)
+                .and_then(|offset| offset.checked_add(start))
+                .and_then(|offset| pixels.get(offset..))
+                .unwrap_or(&[])
+        })
+    }
+
+    fn encode_rows_impl<'a, const N: usize>(
+        &self,
+        sink: &mut impl ByteSink,
+        mut row: impl FnMut(u32) -> &'a [u8],
+    ) -> Result<(), EncodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        if self.width == 0 {
+            return Ok(());
+        }
+
+        let row_len = match (self.width as usize).checked_mul(N) {
+            None => return Err(EncodeError::TooLarge),
+            Some(row_len) => row_len,
+        };
+
+        let mut index = [[0; 4]; 64];
+        let mut px_prev = Pixel::new_opaque();
+        let mut run = 0;
+
+        for y in 0..self.height {
+            let pixels = match row(y).get(..row_len) {
+                None => return Err(EncodeError::NotEnoughPixelData),
+                Some(pixels) => pixels,
+            };
+
+            Self::encode_range_to_sink::<N>(
+                &mut index,
+                &mut px_prev,
+                &mut run,
+                pixels,
+                y + 1 == self.height,
+                sink,
+                &EncodeOptions::DEFAULT,
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use super::*;
+
+use crate::ops::{Iter, Op};
+
+/// Target for decoded pixels.\
+/// Receives pixels of the image in order, row by row.
+///
+/// Allows decoding directly into custom destinations like framebuffers,
+/// GPU staging buffers with row pitch or format converters
+/// without intermediate RGBA buffer.
+pub trait PixelSink {
+    /// Receives next pixel.\
+    /// Pixels of RGB images have alpha set to `255`.
+    fn put_pixel(&mut self, px: [u8; 4]);
+
+    /// Receives `count` copies of the same pixel.\
+    /// Runs are split at row boundaries, so all pixels of a run belong to the same row.
+    #[inline]
+    fn put_run(&mut self, px: [u8; 4], count: usize) {
+        for _ in 0..count {
+            self.put_pixel(px);
+        }
+    }
+
+    /// Called after the last pixel of row `y` is received.
+    #[inline]
+    fn put_row(&mut self, y: u32) {
+        let _ = y;
+    }
+}
+
+impl<S> PixelSink for &mut S
+where
+    S: PixelSink + ?Sized,
+{
+    #[inline]
+    fn put_pixel(&mut self, px: [u8; 4]) {
+        (**self).put_pixel(px)
+    }
+
+    #[inline]
+    fn put_run(&mut self, px: [u8; 4], count: usize) {
+        (**self).put_run(px, count)
+    }
+
+    #[inline]
+    fn put_row(&mut self, y: u32) {
+        (**self).put_row(y)
+    }
+}
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice into `sink`.\
+    /// Pixels are passed to `sink` as soon as they are decoded.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    /// Pixels decoded before the error are passed to `sink`.
+    pub fn decode_to_sink(bytes: &[u8], mut sink: impl PixelSink) -> Result<Self, DecodeError> {
+        let (qoi, ops) = Iter::parse(bytes)?;
+
+        let width = qoi.width as usize;
+        let mut index = [<[u8; 4]>::new(); 64];
+        let mut px = <[u8; 4]>::new_opaque();
+        let mut x = 0;
+        let mut y = 0;
+
+        for op in ops {
+            let op = op?;
+            let mut count = 1;
+
+            match op {
+                Op::Index(i) => px = index[i as usize],
+                Op::Diff { dr, dg, db } => px.add_rgb(dr as u8, dg as u8, db as u8),
+                Op::Luma { dg, dr_dg, db_dg } => px.add_rgb(
+                    dg.wrapping_add(dr_dg) as u8,
+                    dg as u8,
+                    dg.wrapping_add(db_dg) as u8,
+                ),
+                Op::Run(run) => count = run as usize,
+                Op::Rgb(Rgb { r, g, b }) => px.set_rgb(r, g, b),
+                Op::Rgba(Rgba { r, g, b, a }) if qoi.colors.has_alpha() => px.set_rgba(r, g, b, a),
+                // Alpha is ignored in RGB images.
+                Op::Rgba(Rgba { r, g, b, .. }) => px.set_rgb(r, g, b),
+            }
+
+            if !matches!(op, Op::Run(_)) {
+                index[px.hash() as usize] = px;
+            }
+
+            while count > 0 && y < qoi.height {
+                let len = count.min(width - x);
+                match len {
+                    1 => sink.put_pixel(px),
+                    _ => sink.put_run(px, len),
+                }
+                count -= len;
+                x += len;
+
+                if x == width {
+                    sink.put_row(y);
+                    x = 0;
+                    y += 1;
+                }
+            }
+        }
+
+        Ok(qoi)
+    }
+}
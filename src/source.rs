@@ -0,0 +1,114 @@
+use super::*;
+
+use crate::bytes::SliceSink;
+
+/// Source of pixels to encode.\
+/// Provides pixels of the image in order, row by row.
+///
+/// Allows encoding directly from strided buffers, tiled layouts or generators
+/// without tightly packed raw pixels buffer.
+pub trait PixelSource {
+    /// Returns next pixel.\
+    /// Alpha is ignored when encoding RGB images.
+    fn next_pixel(&mut self) -> [u8; 4];
+
+    /// Fills `pixels` with next pixels.\
+    /// Requested pixels never cross row boundaries.
+    #[inline]
+    fn next_pixels(&mut self, pixels: &mut [[u8; 4]]) {
+        for px in pixels {
+            *px = self.next_pixel();
+        }
+    }
+
+    /// Called before the first pixel of row `y` is requested.
+    #[inline]
+    fn next_row(&mut self, y: u32) {
+        let _ = y;
+    }
+}
+
+impl<S> PixelSource for &mut S
+where
+    S: PixelSource + ?Sized,
+{
+    #[inline]
+    fn next_pixel(&mut self) -> [u8; 4] {
+        (**self).next_pixel()
+    }
+
+    #[inline]
+    fn next_pixels(&mut self, pixels: &mut [[u8; 4]]) {
+        (**self).next_pixels(pixels)
+    }
+
+    #[inline]
+    fn next_row(&mut self, y: u32) {
+        (**self).next_row(y)
+    }
+}
+
+impl Qoi {
+    /// Encode pixels pulled from `source` into a QOI image.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_from_source(
+        &self,
+        source: impl PixelSource,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let mut sink = SliceSink::new(output);
+        self.write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)?;
+
+        match self.colors.has_alpha() {
+            true => self.encode_source_impl::<4>(&mut sink, source)?,
+            false => self.encode_source_impl::<3>(&mut sink, source)?,
+        }
+
+        Ok(sink.written())
+    }
+
+    fn encode_source_impl<const N: usize>(
+        &self,
+        sink: &mut SliceSink,
+        mut source: impl PixelSource,
+    ) -> Result<(), EncodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let width = self.width as usize;
+        let mut x = 0;
+        let mut y = 0;
+
+        let mut buffer = [[0; 4]; 256];
+
+        self.encode_chunks::<N>(sink, |chunk| {
+            let mut chunk = chunk;
+
+            while !chunk.is_empty() {
+                if x == 0 {
+                    source.next_row(y);
+                }
+
+                let len = chunk.len().min(width - x).min(buffer.len());
+                let (head, tail) = core::mem::take(&mut chunk).split_at_mut(len);
+
+                source.next_pixels(&mut buffer[..len]);
+                for (px, rgba) in head.iter_mut().zip(&buffer[..len]) {
+                    px.copy_from_slice(&rgba[..N]);
+                }
+
+                chunk = tail;
+                x += len;
+                if x == width {
+                    x = 0;
+                    y += 1;
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
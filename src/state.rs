@@ -0,0 +1,326 @@
+use core::convert::TryInto;
+
+use super::*;
+
+const DEC_STATE_TAG: [u8; 4] = *b"qdst";
+const ENC_STATE_TAG: [u8; 4] = *b"qest";
+
+/// Size of the serialized codec state.\
+/// Tag, color index, previous pixel, run length, number of pixels processed and byte offset.
+const STATE_SIZE: usize = 4 + 64 * 4 + 4 + 4 + 8 + 8;
+
+/// Codec state shared by [`DecState`] and [`EncState`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct State {
+    index: [[u8; 4]; 64],
+    px: [u8; 4],
+    run: usize,
+    pixels: u64,
+    offset: u64,
+}
+
+impl State {
+    const fn new() -> Self {
+        State {
+            index: [[0; 4]; 64],
+            px: [0, 0, 0, 255],
+            run: 0,
+            pixels: 0,
+            offset: 0,
+        }
+    }
+
+    fn serialize(&self, tag: [u8; 4]) -> [u8; STATE_SIZE] {
+        let mut bytes = [0; STATE_SIZE];
+        bytes[0..4].copy_from_slice(&tag);
+        for (dst, src) in bytes[4..260].chunks_exact_mut(4).zip(&self.index) {
+            dst.copy_from_slice(src);
+        }
+        bytes[260..264].copy_from_slice(&self.px);
+        bytes[264..268].copy_from_slice(&(self.run as u32).to_be_bytes());
+        bytes[268..276].copy_from_slice(&self.pixels.to_be_bytes());
+        bytes[276..284].copy_from_slice(&self.offset.to_be_bytes());
+        bytes
+    }
+
+    fn deserialize(bytes: &[u8], tag: [u8; 4]) -> Option<Self> {
+        if bytes.len() != STATE_SIZE || bytes[0..4] != tag {
+            return None;
+        }
+
+        let mut index = [[0; 4]; 64];
+        for (dst, src) in index.iter_mut().zip(bytes[4..260].chunks_exact(4)) {
+            dst.copy_from_slice(src);
+        }
+
+        let run = u32::from_be_bytes(bytes[264..268].try_into().unwrap()) as usize;
+        // Run of 62 pixels is written as op as soon as it is reached, so pending run is at most 61.
+        if run > 61 {
+            return None;
+        }
+
+        Some(State {
+            index,
+            px: bytes[260..264].try_into().unwrap(),
+            run,
+            pixels: u64::from_be_bytes(bytes[268..276].try_into().unwrap()),
+            offset: u64::from_be_bytes(bytes[276..284].try_into().unwrap()),
+        })
+    }
+}
+
+/// Decoder state that can be saved and restored to resume decoding later.\
+/// Holds color index, previous pixel, pending run,
+/// number of pixels decoded and number of encoded bytes consumed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecState {
+    state: State,
+}
+
+impl Default for DecState {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DecState {
+    /// Size of the serialized state in bytes.
+    pub const SIZE: usize = STATE_SIZE;
+
+    /// Returns state to start decoding of the image.
+    #[inline]
+    pub const fn new() -> Self {
+        DecState {
+            state: State::new(),
+        }
+    }
+
+    /// Returns number of pixels decoded so far.
+    #[inline]
+    pub fn pixels(&self) -> u64 {
+        self.state.pixels
+    }
+
+    /// Returns offset of the next encoded byte to decode, counted after the header.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.state.offset
+    }
+
+    /// Returns number of pixels left in the pending run.
+    #[inline]
+    pub(crate) fn run(&self) -> usize {
+        self.state.run
+    }
+
+    /// Serializes state into bytes.
+    #[inline]
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        self.state.serialize(DEC_STATE_TAG)
+    }
+
+    /// Restores state serialized with [`DecState::to_bytes`].\
+    /// Returns `None` if bytes do not contain valid decoder state.
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let state = State::deserialize(bytes, DEC_STATE_TAG)?;
+        Some(DecState { state })
+    }
+}
+
+/// Encoder state that can be saved and restored to resume encoding later.\
+/// Holds color index, previous pixel, pending run,
+/// number of pixels encoded and number of encoded bytes produced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EncState {
+    state: State,
+}
+
+impl Default for EncState {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EncState {
+    /// Size of the serialized state in bytes.
+    pub const SIZE: usize = STATE_SIZE;
+
+    /// Returns state to start encoding of the image.
+    #[inline]
+    pub const fn new() -> Self {
+        EncState {
+            state: State::new(),
+        }
+    }
+
+    /// Returns number of pixels encoded so far.
+    #[inline]
+    pub fn pixels(&self) -> u64 {
+        self.state.pixels
+    }
+
+    /// Returns number of encoded bytes produced so far, not counting the header.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.state.offset
+    }
+
+    /// Serializes state into bytes.
+    #[inline]
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        self.state.serialize(ENC_STATE_TAG)
+    }
+
+    /// Restores state serialized with [`EncState::to_bytes`].\
+    /// Returns `None` if bytes do not contain valid encoder state.
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let state = State::deserialize(bytes, ENC_STATE_TAG)?;
+        Some(EncState { state })
+    }
+}
+
+impl Qoi {
+    #[inline]
+    fn pixels_left(&self, done: u64) -> usize {
+        (self.width as u64 * self.height as u64).saturating_sub(done) as usize
+    }
+
+    /// Decode next pixels of the image continuing from `state`.\
+    /// `bytes` must start at [`DecState::offset`] of the encoded data after the header.\
+    /// Up to `output.len() / channels` pixels are decoded, but no more than left in the image.
+    ///
+    /// State is updated only on success,
+    /// so it can be saved with [`DecState::to_bytes`] and decoding resumed later, even in another process.
+    ///
+    /// On success this function returns `Ok(count)` with `count` of pixels decoded.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_with_state(
+        &self,
+        state: &mut DecState,
+        bytes: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, DecodeError> {
+        match self.colors.has_alpha() {
+            true => self.decode_with_state_impl::<4>(&mut state.state, bytes, output),
+            false => self.decode_with_state_impl::<3>(&mut state.state, bytes, output),
+        }
+    }
+
+    fn decode_with_state_impl<const N: usize>(
+        &self,
+        state: &mut State,
+        bytes: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, DecodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let count = (output.len() / N).min(self.pixels_left(state.pixels));
+
+        let mut index = [[0; N]; 64];
+        for (dst, src) in index.iter_mut().zip(&state.index) {
+            dst.copy_from_slice(&src[..N]);
+        }
+        let mut px = [0; N];
+        px.copy_from_slice(&state.px[..N]);
+        let mut run = state.run;
+
+        let progress = Self::decode_range::<N>(
+            &mut index,
+            &mut px,
+            &mut run,
+            bytes,
+            &mut output[..count * N],
+        )?;
+
+        if progress.pixels < count {
+            return Err(DecodeError::NotEnoughData);
+        }
+
+        for (dst, src) in state.index.iter_mut().zip(&index) {
+            dst[..N].copy_from_slice(src);
+        }
+        state.px[..N].copy_from_slice(&px);
+        state.run = run;
+        state.pixels += count as u64;
+        state.offset += progress.bytes as u64;
+
+        Ok(count)
+    }
+
+    /// Encode next pixels of the image continuing from `state`.\
+    /// Encoded bytes are written into `output` slice.
+    /// Header is not written, see [`Qoi::encode_header`].\
+    /// When last pixel of the image is encoded, end marker is written as well.
+    ///
+    /// State is updated only on success,
+    /// so it can be saved with [`EncState::to_bytes`] and encoding resumed later, even in another process.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded data.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_with_state(
+        &self,
+        state: &mut EncState,
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        match self.colors.has_alpha() {
+            true => self.encode_with_state_impl::<4>(&mut state.state, pixels, output),
+            false => self.encode_with_state_impl::<3>(&mut state.state, pixels, output),
+        }
+    }
+
+    fn encode_with_state_impl<const N: usize>(
+        &self,
+        state: &mut State,
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let left = self.pixels_left(state.pixels);
+        if left == 0 {
+            return Ok(0);
+        }
+
+        let count = (pixels.len() / N).min(left);
+        let last = count == left;
+
+        let mut index = state.index;
+        let mut px_prev = [0; N];
+        px_prev.copy_from_slice(&state.px[..N]);
+        let mut run = state.run;
+
+        let mut size = Self::encode_range_impl::<N>(
+            &mut index,
+            &mut px_prev,
+            &mut run,
+            &pixels[..count * N],
+            output,
+            last,
+        )?;
+
+        if last {
+            let padding = match output.get_mut(size..size + QOI_PADDING) {
+                None => return Err(EncodeError::OutputIsTooSmall),
+                Some(padding) => padding,
+            };
+            padding[..QOI_PADDING - 1].fill(0);
+            padding[QOI_PADDING - 1] = 1;
+            size += QOI_PADDING;
+        }
+
+        state.index = index;
+        state.px[..N].copy_from_slice(&px_prev);
+        state.run = run;
+        state.pixels += count as u64;
+        state.offset += size as u64;
+
+        Ok(size)
+    }
+}
@@ -0,0 +1,106 @@
+//! Op statistics.
+//!
+//! Counts QOI ops and bytes they occupy in encoded image,
+//! either for the whole image or for each row separately.\
+//! Per-row statistics show which image regions compress poorly.
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+use super::*;
+
+use crate::ops::{Iter, Op};
+
+/// Number of ops of each kind and bytes they occupy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OpStats {
+    /// Number of `QOI_OP_INDEX` ops.
+    pub index: u64,
+
+    /// Number of `QOI_OP_DIFF` ops.
+    pub diff: u64,
+
+    /// Number of `QOI_OP_LUMA` ops.
+    pub luma: u64,
+
+    /// Number of `QOI_OP_RUN` ops.
+    pub run: u64,
+
+    /// Number of `QOI_OP_RGB` ops.
+    pub rgb: u64,
+
+    /// Number of `QOI_OP_RGBA` ops.
+    pub rgba: u64,
+
+    /// Number of encoded bytes.
+    pub bytes: u64,
+}
+
+impl OpStats {
+    /// Returns total number of ops.
+    #[inline]
+    pub fn ops(&self) -> u64 {
+        self.index + self.diff + self.luma + self.run + self.rgb + self.rgba
+    }
+
+    #[inline]
+    fn add(&mut self, op: &Op) {
+        match op {
+            Op::Index(_) => self.index += 1,
+            Op::Diff { .. } => self.diff += 1,
+            Op::Luma { .. } => self.luma += 1,
+            Op::Run(_) => self.run += 1,
+            Op::Rgb(_) => self.rgb += 1,
+            Op::Rgba(_) => self.rgba += 1,
+        }
+        self.bytes += op.size() as u64;
+    }
+}
+
+/// Walks over ops of the image.\
+/// `f` is called with index of the first pixel produced by the op and the op.
+fn walk_ops(qoi: &Qoi, bytes: &[u8], mut f: impl FnMut(u64, &Op)) -> Result<(), DecodeError> {
+    let mut pos = 0;
+    for op in Iter::with_header(qoi, bytes) {
+        let op = op?;
+        f(pos, &op);
+        pos += op.pixels() as u64;
+    }
+
+    Ok(())
+}
+
+/// Collects op statistics of the whole image.
+///
+/// On success this function returns `Ok((qoi, stats))` with `qoi` describing image dimensions and color space.\
+/// On failure this function returns `Err(err)` with `err` describing cause of the error.
+pub fn collect(bytes: &[u8]) -> Result<(Qoi, OpStats), DecodeError> {
+    let qoi = Qoi::decode_header(bytes)?;
+
+    let mut stats = OpStats::default();
+    walk_ops(&qoi, &bytes[QOI_HEADER_SIZE..], |_, op| stats.add(op))?;
+
+    Ok((qoi, stats))
+}
+
+/// Collects op statistics for each row of the image.\
+/// Op is accounted in the row of the first pixel it produces,
+/// so runs spanning several rows are accounted once.
+///
+/// On success this function returns `Ok((qoi, rows))` with `qoi` describing image dimensions and color space
+/// and `rows` containing statistics for each row.\
+/// On failure this function returns `Err(err)` with `err` describing cause of the error.
+#[cfg(feature = "alloc")]
+pub fn collect_rows(bytes: &[u8]) -> Result<(Qoi, Vec<OpStats>), DecodeError> {
+    let qoi = Qoi::decode_header(bytes)?;
+
+    let mut rows = vec![OpStats::default(); qoi.height as usize];
+    if qoi.width > 0 {
+        let width = qoi.width as u64;
+        walk_ops(&qoi, &bytes[QOI_HEADER_SIZE..], |pos, op| {
+            rows[(pos / width) as usize].add(op)
+        })?;
+    }
+
+    Ok((qoi, rows))
+}
@@ -0,0 +1,190 @@
+//! Integration with [`tokio`](::tokio) crate.
+
+use std::io;
+
+use ::bytes::{Buf, BufMut, BytesMut};
+use ::tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use ::tokio_util::codec;
+
+use super::*;
+
+use crate::io::{
+    invalid_data, invalid_input, ChunkDecoder, ChunkEncoder, MAX_PIXELS_PER_BYTE, READ_BUFFER_SIZE,
+};
+
+/// Size of the frame length prefix.
+const LENGTH_SIZE: usize = 4;
+
+/// Default maximum length of the encoded frame.
+const DEFAULT_MAX_LENGTH: usize = 64 * 1024 * 1024;
+
+impl Qoi {
+    /// Encode raw RGB or RGBA pixels into a QOI image and write it into tokio `writer`.\
+    /// Pixels are encoded in small chunks, each written as soon as it is encoded.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] wrapping [`EncodeError::NotEnoughPixelData`]
+    /// if `pixels` is too small for the image.
+    pub async fn encode_tokio<W>(&self, pixels: &[u8], writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut encoder = ChunkEncoder::new(self, pixels).map_err(invalid_input)?;
+        while let Some(bytes) = encoder.next().map_err(invalid_input)? {
+            writer.write_all(bytes).await?;
+        }
+
+        writer.flush().await
+    }
+
+    /// Read and decode a QOI image from tokio `reader`.\
+    /// Decoded raw RGB or RGBA pixels are written into allocated `Vec`,
+    /// which grows as encoded data is read.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] wrapping [`DecodeError`] if image is invalid
+    /// and with [`io::ErrorKind::UnexpectedEof`] if reader ends before the last pixel.
+    pub async fn decode_tokio<R>(reader: &mut R) -> io::Result<(Self, Vec<u8>)>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut header = [0; QOI_HEADER_SIZE];
+        reader.read_exact(&mut header).await?;
+
+        let mut decoder = ChunkDecoder::new(&header).map_err(invalid_data)?;
+
+        let mut buffer = vec![0; READ_BUFFER_SIZE];
+        while !decoder.is_done() {
+            let len = reader.read(&mut buffer).await?;
+            if len == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            decoder.feed(&buffer[..len]).map_err(invalid_data)?;
+        }
+
+        Ok(decoder.finish())
+    }
+}
+
+/// Codec for length-prefixed QOI frames.
+///
+/// Each frame is a big-endian `u32` length followed by complete QOI image of that length.\
+/// Decodes frames into `(qoi, pixels)` pairs and encodes `(qoi, pixels)` pairs into frames.
+/// Use with [`Framed`](::tokio_util::codec::Framed) to send images over a byte stream.
+///
+/// Frames longer than maximum length are rejected with [`io::ErrorKind::InvalidData`]
+/// when decoding and with [`io::ErrorKind::InvalidInput`] when encoding.
+#[derive(Clone, Copy, Debug)]
+pub struct QoiCodec {
+    max_length: usize,
+}
+
+impl Default for QoiCodec {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QoiCodec {
+    /// Returns new codec with default maximum frame length of 64 MiB.
+    #[inline]
+    pub const fn new() -> Self {
+        QoiCodec {
+            max_length: DEFAULT_MAX_LENGTH,
+        }
+    }
+
+    /// Returns new codec with specified maximum frame length.\
+    /// Length prefix is not included.
+    #[inline]
+    pub const fn with_max_length(max_length: usize) -> Self {
+        QoiCodec { max_length }
+    }
+
+    /// Returns maximum frame length.
+    #[inline]
+    pub const fn max_length(&self) -> usize {
+        self.max_length
+    }
+}
+
+impl codec::Decoder for QoiCodec {
+    type Item = (Qoi, Vec<u8>);
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<(Qoi, Vec<u8>)>> {
+        if src.len() < LENGTH_SIZE {
+            return Ok(None);
+        }
+
+        let mut prefix = [0; LENGTH_SIZE];
+        prefix.copy_from_slice(&src[..LENGTH_SIZE]);
+        let length = u32::from_be_bytes(prefix) as usize;
+
+        if length > self.max_length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "QOI frame is too long",
+            ));
+        }
+
+        if src.len() < LENGTH_SIZE + length {
+            src.reserve(LENGTH_SIZE + length - src.len());
+            return Ok(None);
+        }
+
+        src.advance(LENGTH_SIZE);
+        let frame = src.split_to(length);
+
+        // Reject headers that claim more pixels than the frame can encode
+        // before allocating output.
+        let qoi = Qoi::decode_header(&frame).map_err(invalid_data)?;
+        if qoi.width as u64 * qoi.height as u64 > length as u64 * MAX_PIXELS_PER_BYTE as u64 {
+            return Err(invalid_data(DecodeError::NotEnoughData));
+        }
+
+        Qoi::decode_alloc(&frame).map(Some).map_err(invalid_data)
+    }
+}
+
+impl<P> codec::Encoder<(Qoi, P)> for QoiCodec
+where
+    P: AsRef<[u8]>,
+{
+    type Error = io::Error;
+
+    fn encode(&mut self, (qoi, pixels): (Qoi, P), dst: &mut BytesMut) -> io::Result<()> {
+        let pixels = qoi
+            .image_pixels(pixels.as_ref(), qoi.colors.channels())
+            .map_err(invalid_input)?;
+
+        // Output of this size fails to fit only if frame exceeds maximum length.
+        let limit = qoi
+            .encoded_size_limit()
+            .min(self.max_length)
+            .min(u32::MAX as usize);
+        let start = dst.len();
+
+        dst.reserve(LENGTH_SIZE + limit);
+        dst.put_bytes(0, LENGTH_SIZE + limit);
+
+        let length = match qoi.encode(pixels, &mut dst[start + LENGTH_SIZE..]) {
+            Ok(length) => length,
+            Err(EncodeError::OutputIsTooSmall) => {
+                dst.truncate(start);
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "QOI frame is too long",
+                ));
+            }
+            Err(err) => {
+                dst.truncate(start);
+                return Err(invalid_input(err));
+            }
+        };
+
+        dst.truncate(start + LENGTH_SIZE + length);
+        dst[start..][..LENGTH_SIZE].copy_from_slice(&(length as u32).to_be_bytes());
+        Ok(())
+    }
+}
@@ -0,0 +1,463 @@
+use super::*;
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+use crate::bytes::SliceSink;
+
+/// Number of typed pixels converted at once by range functions.
+const PIXELS_CHUNK: usize = 256;
+
+/// Typed pixel accepted by [`Qoi::encode_pixels`].\
+/// Implemented for [`Rgb`] and [`Rgba`], and for `rgb::RGB8` and `rgb::RGBA8` with `rgb` feature enabled.\
+/// Pixel type defines whether encoded image has alpha channel.
+pub trait EncodePixel: Copy {
+    /// `true` if pixel type has alpha channel.
+    const HAS_ALPHA: bool;
+
+    /// Returns channels of the pixel.\
+    /// Alpha is `255` for pixel types without alpha channel.
+    fn rgba(&self) -> [u8; 4];
+}
+
+impl EncodePixel for Rgb {
+    const HAS_ALPHA: bool = false;
+
+    #[inline]
+    fn rgba(&self) -> [u8; 4] {
+        [self.r, self.g, self.b, 255]
+    }
+}
+
+impl EncodePixel for Rgba {
+    const HAS_ALPHA: bool = true;
+
+    #[inline]
+    fn rgba(&self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+/// Implements [`Pixel`] and [`EncodePixel`] for user-defined pixel struct.\
+/// Struct must be `Copy + Eq` and consist only of `u8` channel fields,
+/// listed in red, green, blue and optional alpha order regardless of struct layout.
+///
+/// Every method forwards to the `[u8; 3]` or `[u8; 4]` implementation,
+/// so channels are always read and written in RGB(A) order.\
+/// Such pixels can be encoded with [`Qoi::encode_pixels`] and [`Qoi::encode_range_pixels`]
+/// and decoded with [`Qoi::decode_range_pixels`].
+///
+/// # Example
+///
+/// ```
+/// use rapid_qoi::{Pixel, Qoi};
+///
+/// #[derive(Clone, Copy, PartialEq, Eq)]
+/// struct Bgra {
+///     b: u8,
+///     g: u8,
+///     r: u8,
+///     a: u8,
+/// }
+///
+/// rapid_qoi::impl_pixel!(Bgra { r, g, b, a });
+///
+/// let pixels = [Bgra { b: 3, g: 2, r: 1, a: 4 }; 4];
+///
+/// let mut output = [0; 64];
+/// let size = Qoi::encode_range_pixels(
+///     &mut [[0; 4]; 64],
+///     &mut Bgra::new_opaque(),
+///     &mut 0,
+///     &pixels,
+///     &mut output,
+/// )
+/// .unwrap();
+/// assert_eq!(output[..size], [0xff, 1, 2, 3, 4, 0xc2]);
+///
+/// let mut decoded = [Bgra::new(); 4];
+/// Qoi::decode_range_pixels(
+///     &mut [Bgra::new(); 64],
+///     &mut Bgra::new_opaque(),
+///     &mut 0,
+///     &output[..size],
+///     &mut decoded,
+/// )
+/// .unwrap();
+/// assert!(decoded == pixels);
+/// ```
+#[macro_export]
+macro_rules! impl_pixel {
+    ($ty:ident { $r:ident, $g:ident, $b:ident }) => {
+        $crate::impl_pixel!(@impl $ty, 3, false, [$r, $g, $b]);
+    };
+    ($ty:ident { $r:ident, $g:ident, $b:ident, $a:ident }) => {
+        $crate::impl_pixel!(@impl $ty, 4, true, [$r, $g, $b, $a]);
+    };
+    (@to $px:expr, [$($f:ident),*]) => {
+        [$($px.$f),*]
+    };
+    (@from $arr:expr, $ty:ident, [$($f:ident),*]) => {{
+        let [$($f),*] = $arr;
+        $ty { $($f),* }
+    }};
+    (@set $px:expr, $ty:ident, [$($f:ident),*], $method:ident($($arg:expr),*)) => {{
+        let mut arr = $crate::impl_pixel!(@to $px, [$($f),*]);
+        $crate::Pixel::$method(&mut arr, $($arg),*);
+        *$px = $crate::impl_pixel!(@from arr, $ty, [$($f),*]);
+    }};
+    (@impl $ty:ident, $n:literal, $alpha:literal, $fs:tt) => {
+        impl $crate::Pixel for $ty {
+            const HAS_ALPHA: bool = $alpha;
+
+            #[inline]
+            fn new() -> Self {
+                $crate::impl_pixel!(@from <[u8; $n] as $crate::Pixel>::new(), $ty, $fs)
+            }
+
+            #[inline]
+            fn new_opaque() -> Self {
+                $crate::impl_pixel!(@from <[u8; $n] as $crate::Pixel>::new_opaque(), $ty, $fs)
+            }
+
+            #[inline]
+            fn read(&mut self, bytes: &[u8]) {
+                $crate::impl_pixel!(@set self, $ty, $fs, read(bytes))
+            }
+
+            #[inline]
+            fn write(&self, bytes: &mut [u8]) {
+                $crate::Pixel::write(&$crate::impl_pixel!(@to self, $fs), bytes)
+            }
+
+            #[inline]
+            fn var(&self, prev: &Self) -> $crate::Var {
+                $crate::Pixel::var(
+                    &$crate::impl_pixel!(@to self, $fs),
+                    &$crate::impl_pixel!(@to prev, $fs),
+                )
+            }
+
+            #[inline]
+            fn rgb(&self) -> [u8; 3] {
+                $crate::Pixel::rgb(&$crate::impl_pixel!(@to self, $fs))
+            }
+
+            #[inline]
+            fn rgba(&self) -> [u8; 4] {
+                $crate::Pixel::rgba(&$crate::impl_pixel!(@to self, $fs))
+            }
+
+            #[inline]
+            fn r(&self) -> u8 {
+                $crate::Pixel::r(&$crate::impl_pixel!(@to self, $fs))
+            }
+
+            #[inline]
+            fn g(&self) -> u8 {
+                $crate::Pixel::g(&$crate::impl_pixel!(@to self, $fs))
+            }
+
+            #[inline]
+            fn b(&self) -> u8 {
+                $crate::Pixel::b(&$crate::impl_pixel!(@to self, $fs))
+            }
+
+            #[inline]
+            fn a(&self) -> u8 {
+                $crate::Pixel::a(&$crate::impl_pixel!(@to self, $fs))
+            }
+
+            #[inline]
+            fn set_r(&mut self, r: u8) {
+                $crate::impl_pixel!(@set self, $ty, $fs, set_r(r))
+            }
+
+            #[inline]
+            fn set_g(&mut self, g: u8) {
+                $crate::impl_pixel!(@set self, $ty, $fs, set_g(g))
+            }
+
+            #[inline]
+            fn set_b(&mut self, b: u8) {
+                $crate::impl_pixel!(@set self, $ty, $fs, set_b(b))
+            }
+
+            #[inline]
+            fn set_a(&mut self, a: u8) {
+                $crate::impl_pixel!(@set self, $ty, $fs, set_a(a))
+            }
+
+            #[inline]
+            fn set_rgb(&mut self, r: u8, g: u8, b: u8) {
+                $crate::impl_pixel!(@set self, $ty, $fs, set_rgb(r, g, b))
+            }
+
+            #[inline]
+            fn set_rgba(&mut self, r: u8, g: u8, b: u8, a: u8) {
+                $crate::impl_pixel!(@set self, $ty, $fs, set_rgba(r, g, b, a))
+            }
+
+            #[inline]
+            fn add_rgb(&mut self, r: u8, g: u8, b: u8) {
+                $crate::impl_pixel!(@set self, $ty, $fs, add_rgb(r, g, b))
+            }
+
+            #[inline]
+            fn hash(&self) -> u8 {
+                $crate::Pixel::hash(&$crate::impl_pixel!(@to self, $fs))
+            }
+        }
+
+        impl $crate::EncodePixel for $ty {
+            const HAS_ALPHA: bool = $alpha;
+
+            #[inline]
+            fn rgba(&self) -> [u8; 4] {
+                $crate::Pixel::rgba(&$crate::impl_pixel!(@to self, $fs))
+            }
+        }
+    };
+}
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels are written into `output` slice of [`Rgba`] values.\
+    /// Alpha channel of images without one is set to `255`.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_pixels(bytes: &[u8], output: &mut [Rgba]) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let px_count = qoi.pixel_count()?;
+        let output = match output.get_mut(..px_count) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+        let mut output = output.iter_mut();
+
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_chunks::<4>(bytes, |pixels| {
+                for (px, out) in pixels.iter().zip(output.by_ref()) {
+                    *out = Rgba::from(*px);
+                }
+            })?,
+            false => qoi.decode_chunks::<3>(bytes, |pixels| {
+                for (px, out) in pixels.iter().zip(output.by_ref()) {
+                    *out = Rgba::from(px.rgba());
+                }
+            })?,
+        }
+
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels are written into allocated `Vec` of [`Rgba`] values.\
+    /// Alpha channel of images without one is set to `255`.
+    ///
+    /// On success this function returns `Ok((qoi, vec))` with `qoi` describing image dimensions and color space and `vec` containing pixels.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn decode_alloc_pixels(bytes: &[u8]) -> Result<(Self, Vec<Rgba>), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let mut output = vec![Rgba::default(); qoi.pixel_count()?];
+        let qoi = Self::decode_pixels(bytes, &mut output)?;
+        Ok((qoi, output))
+    }
+
+    /// Encode typed pixels into a QOI image.\
+    /// Presence of alpha channel in the image is defined by pixel type,
+    /// so it always agrees with `pixels`. Only color channels encoding is taken from `self.colors`.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_pixels<P>(&self, pixels: &[P], output: &mut [u8]) -> Result<usize, EncodeError>
+    where
+        P: EncodePixel,
+    {
+        let qoi = Qoi {
+            colors: self.colors.with_alpha(P::HAS_ALPHA),
+            ..*self
+        };
+
+        let pixels = qoi.image_pixels(pixels, 1)?;
+
+        let mut sink = SliceSink::new(output);
+        qoi.write_header_to_sink(QOI_MAGIC.to_be_bytes(), &mut sink)?;
+
+        let mut source = pixels.iter();
+
+        match P::HAS_ALPHA {
+            true => qoi.encode_chunks::<4>(&mut sink, |chunk| {
+                for (px, src) in chunk.iter_mut().zip(source.by_ref()) {
+                    *px = src.rgba();
+                }
+                Ok(())
+            })?,
+            false => qoi.encode_chunks::<3>(&mut sink, |chunk| {
+                for (px, src) in chunk.iter_mut().zip(source.by_ref()) {
+                    px.copy_from_slice(&src.rgba()[..3]);
+                }
+                Ok(())
+            })?,
+        }
+
+        Ok(sink.written())
+    }
+
+    /// Encode range of typed pixels into output slice.\
+    /// Same as [`Qoi::encode_range`], with pixel type defining number of channels.
+    /// Pixels are converted to RGB(A) bytes in small chunks on the stack.
+    pub fn encode_range_pixels<P>(
+        index: &mut [[u8; 4]; 64],
+        px_prev: &mut P,
+        run: &mut usize,
+        pixels: &[P],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError>
+    where
+        P: Pixel,
+    {
+        if *run > 61 {
+            return Err(EncodeError::InvalidOp);
+        }
+
+        match P::HAS_ALPHA {
+            true => Self::encode_range_pixels_impl::<P, 4>(index, px_prev, run, pixels, output),
+            false => Self::encode_range_pixels_impl::<P, 3>(index, px_prev, run, pixels, output),
+        }
+    }
+
+    fn encode_range_pixels_impl<P, const N: usize>(
+        index: &mut [[u8; 4]; 64],
+        px_prev: &mut P,
+        run: &mut usize,
+        pixels: &[P],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError>
+    where
+        P: Pixel,
+        [u8; N]: Pixel,
+    {
+        let mut prev = [0; N];
+        px_prev.write(&mut prev);
+
+        let mut buffer = [[0; N]; PIXELS_CHUNK];
+        let mut size = 0;
+        let mut result = Ok(());
+
+        let count = pixels.len().div_ceil(PIXELS_CHUNK);
+        for (i, chunk) in pixels.chunks(PIXELS_CHUNK).enumerate() {
+            let bytes = &mut buffer[..chunk.len()];
+            for (dst, src) in bytes.iter_mut().zip(chunk) {
+                src.write(dst);
+            }
+
+            match Self::encode_range_impl::<N>(
+                index,
+                &mut prev,
+                run,
+                bytes.as_flattened(),
+                output.get_mut(size..).unwrap_or_default(),
+                i + 1 == count,
+            ) {
+                Ok(written) => size += written,
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+
+        px_prev.read(&prev);
+        result.map(|()| size)
+    }
+
+    /// Decode range of pixels into typed pixels slice.\
+    /// Same as [`Qoi::decode_range`], with pixel type defining number of channels.
+    /// Pixels are decoded into RGB(A) bytes in small chunks on the stack and then converted.
+    pub fn decode_range_pixels<P>(
+        index: &mut [P; 64],
+        ppx: &mut P,
+        prun: &mut usize,
+        bytes: &[u8],
+        pixels: &mut [P],
+    ) -> Result<RangeProgress, DecodeError>
+    where
+        P: Pixel,
+    {
+        match P::HAS_ALPHA {
+            true => Self::decode_range_pixels_impl::<P, 4>(index, ppx, prun, bytes, pixels),
+            false => Self::decode_range_pixels_impl::<P, 3>(index, ppx, prun, bytes, pixels),
+        }
+    }
+
+    fn decode_range_pixels_impl<P, const N: usize>(
+        index: &mut [P; 64],
+        ppx: &mut P,
+        prun: &mut usize,
+        bytes: &[u8],
+        pixels: &mut [P],
+    ) -> Result<RangeProgress, DecodeError>
+    where
+        P: Pixel,
+        [u8; N]: Pixel,
+    {
+        let mut raw_index = [[0; N]; 64];
+        for (dst, src) in raw_index.iter_mut().zip(index.iter()) {
+            src.write(dst);
+        }
+        let mut px = [0; N];
+        ppx.write(&mut px);
+
+        let mut buffer = [[0; N]; PIXELS_CHUNK];
+        let mut consumed = 0;
+        let mut written = 0;
+        let mut result = Ok(());
+
+        for chunk in pixels.chunks_mut(PIXELS_CHUNK) {
+            let raw = &mut buffer[..chunk.len()];
+            let progress = match Self::decode_range::<N>(
+                &mut raw_index,
+                &mut px,
+                prun,
+                bytes.get(consumed..).unwrap_or_default(),
+                raw.as_flattened_mut(),
+            ) {
+                Ok(progress) => progress,
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            };
+
+            for (dst, src) in chunk.iter_mut().zip(&raw[..progress.pixels]) {
+                dst.read(src);
+            }
+            consumed += progress.bytes;
+            written += progress.pixels;
+
+            if progress.pixels < chunk.len() {
+                break;
+            }
+        }
+
+        for (dst, src) in index.iter_mut().zip(&raw_index) {
+            dst.read(src);
+        }
+        ppx.read(&px);
+
+        result.map(|()| RangeProgress {
+            bytes: consumed,
+            pixels: written,
+            run: *prun,
+        })
+    }
+}
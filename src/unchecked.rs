@@ -0,0 +1,103 @@
+//! Pointer-based inner loops enabled by `unsafe-perf` feature.\
+//! Safe loops stay the default and handle everything these loops leave unprocessed.
+
+#![allow(unsafe_code)]
+
+use super::*;
+
+/// Maximum size of single op.
+const MAX_OP_SIZE: usize = 5;
+
+/// Decodes ops from `bytes` into `pixels` while at least [`MAX_OP_SIZE`] bytes are left,
+/// so no op can be truncated and no length checks are required.\
+/// Stops before a run that does not fit into `pixels`.
+///
+/// Returns number of consumed bytes and written pixels.
+#[inline]
+pub(crate) fn decode_ops<const N: usize>(
+    index: &mut [[u8; N]; 64],
+    ppx: &mut [u8; N],
+    bytes: &[u8],
+    pixels: &mut [[u8; N]],
+) -> (usize, usize)
+where
+    [u8; N]: Pixel,
+{
+    let mut px = *ppx;
+
+    let bytes_len = bytes.len();
+    let pixels_len = pixels.len();
+    let src = bytes.as_ptr();
+    let dst = pixels.as_mut_ptr();
+    let mut i = 0;
+    let mut o = 0;
+
+    // SAFETY: Loop condition keeps `i + MAX_OP_SIZE <= bytes_len`,
+    // so all bytes of the current op are in bounds of `bytes`.
+    // Loop condition keeps `o < pixels_len` and runs are checked to fit before written,
+    // so all written pixels are in bounds of `pixels`.
+    unsafe {
+        while o < pixels_len && i + MAX_OP_SIZE <= bytes_len {
+            let b1 = *src.add(i);
+
+            match b1 {
+                0b00000000..=0b00111111 => {
+                    px = *index.get_unchecked(b1 as usize);
+                    *dst.add(o) = px;
+                    o += 1;
+                    i += 1;
+                    continue;
+                }
+                0b01000000..=0b01111111 => {
+                    let vr = ((b1 >> 4) & 0x03).wrapping_sub(2);
+                    let vg = ((b1 >> 2) & 0x03).wrapping_sub(2);
+                    let vb = (b1 & 0x03).wrapping_sub(2);
+                    px.add_rgb(vr, vg, vb);
+                    i += 1;
+                }
+                0b10000000..=0b10111111 => {
+                    let b2 = *src.add(i + 1);
+                    let vg = (b1 & 0x3f).wrapping_sub(32);
+                    let vr = ((b2 >> 4) & 0x0f).wrapping_sub(8).wrapping_add(vg);
+                    let vb = (b2 & 0x0f).wrapping_sub(8).wrapping_add(vg);
+                    px.add_rgb(vr, vg, vb);
+                    i += 2;
+                }
+                0b11111110 => {
+                    px.set_rgb(*src.add(i + 1), *src.add(i + 2), *src.add(i + 3));
+                    i += 4;
+                }
+                0b11111111 => {
+                    let (r, g, b, a) = (
+                        *src.add(i + 1),
+                        *src.add(i + 2),
+                        *src.add(i + 3),
+                        *src.add(i + 4),
+                    );
+                    match N {
+                        3 => px.set_rgb(r, g, b),
+                        _ => px.set_rgba(r, g, b, a),
+                    }
+                    i += 5;
+                }
+                _ => {
+                    let len = (b1 & 0x3f) as usize + 1;
+                    if len > pixels_len - o {
+                        break;
+                    }
+                    core::slice::from_raw_parts_mut(dst.add(o), len).fill(px);
+                    o += len;
+                    i += 1;
+                    continue;
+                }
+            }
+
+            *index.get_unchecked_mut(px.hash() as usize) = px;
+            *dst.add(o) = px;
+            o += 1;
+        }
+    }
+
+    *ppx = px;
+    (i, o)
+}
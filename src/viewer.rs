@@ -0,0 +1,75 @@
+//! Simple window to look at QOI images.
+//!
+//! Pixels are decoded with [`Qoi::decode_u32`] straight into window framebuffer.
+
+use minifb::{Key, Window, WindowOptions};
+
+use super::*;
+
+/// Errors that may occur when showing an image.
+#[derive(Debug)]
+pub enum ViewError {
+    /// Failed to decode image.
+    Decode(DecodeError),
+
+    /// Failed to open or update the window.
+    Window(minifb::Error),
+}
+
+impl Display for ViewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViewError::Decode(err) => write!(f, "Failed to decode image. {}", err),
+            ViewError::Window(err) => write!(f, "Window error. {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ViewError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ViewError::Decode(err) => Some(err),
+            ViewError::Window(err) => Some(err),
+        }
+    }
+}
+
+impl From<DecodeError> for ViewError {
+    #[inline]
+    fn from(err: DecodeError) -> Self {
+        ViewError::Decode(err)
+    }
+}
+
+impl From<minifb::Error> for ViewError {
+    #[inline]
+    fn from(err: minifb::Error) -> Self {
+        ViewError::Window(err)
+    }
+}
+
+/// Decode a QOI image from bytes slice and show it in a window.\
+/// Blocks until the window is closed or `Escape` is pressed.
+pub fn show(title: &str, bytes: &[u8]) -> Result<(), ViewError> {
+    let qoi = Qoi::decode_header(bytes)?;
+
+    let mut framebuffer = vec![0; qoi.pixel_count()?];
+    Qoi::decode_u32(bytes, PackedLayout::Argb, &mut framebuffer)?;
+
+    let mut window = Window::new(
+        title,
+        qoi.width as usize,
+        qoi.height as usize,
+        WindowOptions {
+            resize: true,
+            ..WindowOptions::default()
+        },
+    )?;
+    window.set_target_fps(30);
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        window.update_with_buffer(&framebuffer, qoi.width as usize, qoi.height as usize)?;
+    }
+
+    Ok(())
+}
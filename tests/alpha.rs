@@ -0,0 +1,27 @@
+//! [`all_opaque`] must check every whole pixel and ignore trailing bytes.
+
+use rapid_qoi::alpha::all_opaque;
+
+#[test]
+fn all_opaque_ignores_trailing_bytes() {
+    let opaque = [255u8; 4 * 40];
+
+    for len in 0..opaque.len() {
+        let whole = len / 4 * 4;
+        assert!(all_opaque(&opaque[..len]));
+
+        // Transparent pixel is found in blocks and in remainder.
+        for i in (3..whole).step_by(4) {
+            let mut pixels = opaque;
+            pixels[i] = 0;
+            assert!(!all_opaque(&pixels[..len]), "{} of {}", i, len);
+        }
+
+        // Trailing byte is not alpha.
+        if len > whole {
+            let mut pixels = opaque;
+            pixels[len - 1] = 0;
+            assert!(all_opaque(&pixels[..len]));
+        }
+    }
+}
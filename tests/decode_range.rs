@@ -0,0 +1,38 @@
+//! Tests for resumable decoding with [`Qoi::decode_range`].
+
+use rapid_qoi::{Qoi, RangeProgress};
+
+/// Run longer than the rest of pixels slice is carried into the next call instead of panicking.
+#[test]
+fn run_past_end_of_pixels() {
+    let mut index = [[0; 4]; 64];
+    let mut px = [0, 0, 0, 255];
+    let mut run = 0;
+
+    // QOI_OP_RGBA followed by QOI_OP_RUN of 62 pixels.
+    let bytes = [0xff, 1, 2, 3, 4, 0xfd];
+
+    let mut pixels = [0; 3 * 4];
+    let progress = Qoi::decode_range::<4>(&mut index, &mut px, &mut run, &bytes, &mut pixels);
+    assert_eq!(
+        progress,
+        Ok(RangeProgress {
+            bytes: 6,
+            pixels: 3,
+            run: 60,
+        })
+    );
+    assert_eq!(pixels, [1, 2, 3, 4].repeat(3)[..]);
+
+    let mut pixels = [0; 64 * 4];
+    let progress = Qoi::decode_range::<4>(&mut index, &mut px, &mut run, &[], &mut pixels);
+    assert_eq!(
+        progress,
+        Ok(RangeProgress {
+            bytes: 0,
+            pixels: 60,
+            run: 0,
+        })
+    );
+    assert_eq!(pixels[..60 * 4], [1, 2, 3, 4].repeat(60)[..]);
+}
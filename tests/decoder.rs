@@ -0,0 +1,91 @@
+//! Feeding [`Decoder`] in fragments of any size must produce the same pixels as [`Qoi::decode`].
+
+use rapid_qoi::{Colors, Decoder, Qoi};
+
+/// Returns encoded image with every op kind, runs longer than 62 pixels
+/// and literal ops whose bytes get split between fragments.
+fn encoded(colors: Colors) -> Vec<u8> {
+    let qoi = Qoi {
+        width: 61,
+        height: 7,
+        colors,
+    };
+
+    let channels = colors.channels();
+    let mut pixels = Vec::new();
+    let mut px = [0u8, 0, 0, 255];
+    for i in 0..qoi.width * qoi.height {
+        match i % 97 {
+            // Long run.
+            0..=69 => {}
+            // Small differences.
+            70..=74 => px[0] = px[0].wrapping_add(1),
+            // Luma.
+            75..=79 => {
+                px[1] = px[1].wrapping_add(20);
+                px[0] = px[0].wrapping_add(23);
+            }
+            // Repeated colors from index.
+            80..=84 => px = [1, 2, 3, 255],
+            85..=89 => px = [4, 5, 6, 255],
+            // Literals, with alpha changes for RGBA.
+            _ => {
+                px = [
+                    (i * 37) as u8,
+                    (i * 91) as u8,
+                    (i * 13) as u8,
+                    (i * 7) as u8,
+                ]
+            }
+        }
+        pixels.extend_from_slice(&px[..channels]);
+    }
+
+    let mut bytes = vec![0; qoi.encoded_size_limit()];
+    let size = qoi.encode(&pixels, &mut bytes).unwrap();
+    bytes.truncate(size);
+    bytes
+}
+
+fn decode_fragments(bytes: &[u8], fragment: usize) -> Vec<u8> {
+    let mut decoder = Decoder::new();
+    let mut output = Vec::new();
+    let mut pos = 0;
+
+    for mut chunk in bytes.chunks(fragment) {
+        while !chunk.is_empty() && !decoder.is_done() {
+            if let Some(qoi) = decoder.qoi() {
+                output.resize(qoi.decoded_size(), 0);
+            }
+
+            let progress = decoder.feed(chunk, &mut output[pos..]).unwrap();
+            pos += progress.pixels * decoder.qoi().map_or(0, |qoi| qoi.colors.channels());
+            chunk = &chunk[progress.bytes..];
+        }
+    }
+
+    assert!(decoder.is_done());
+    assert_eq!(pos, output.len());
+    output
+}
+
+#[test]
+fn feed_fragments() {
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let bytes = encoded(colors);
+
+        let qoi = Qoi::decode_header(&bytes).unwrap();
+        let mut expected = vec![0; qoi.decoded_size()];
+        Qoi::decode(&bytes, &mut expected).unwrap();
+
+        // Sizes below 14 split the header, sizes not dividing op sizes split ops.
+        for fragment in [1, 2, 3, 4, 5, 7, 13, 64, 1000, bytes.len()] {
+            assert_eq!(
+                decode_fragments(&bytes, fragment),
+                expected,
+                "fragment of {} bytes",
+                fragment
+            );
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Compares decoding of whole image with decoding fed a few bytes at a time.\
+//! With `unsafe-perf` feature whole image goes through unchecked loop,
+//! while at most 4 bytes fed at once never satisfy its minimum of 5 bytes,
+//! leaving those bytes to safe loop. RGBA ops that don't fit are fed as 5 bytes.\
+//! Without the feature both paths use safe loop.
+
+#![cfg(feature = "alloc")]
+
+use rapid_qoi::{Colors, DecodeError, Pixel, Qoi};
+
+/// Size of QOI header in bytes.
+const QOI_HEADER_SIZE: usize = 14;
+
+/// Xorshift generator, good enough to produce test images.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// Returns image mixing runs, small differences, repeated colors and random pixels,
+/// so every op kind appears in encoded stream.
+fn image(rng: &mut Rng) -> (Qoi, Vec<u8>) {
+    let qoi = Qoi {
+        width: 1 + rng.below(64) as u32,
+        height: 1 + rng.below(64) as u32,
+        colors: match rng.below(2) {
+            0 => Colors::Srgb,
+            _ => Colors::SrgbLinA,
+        },
+    };
+
+    let n = qoi.colors.channels();
+    let palette: Vec<u8> = (0..8 * n).map(|_| rng.next() as u8).collect();
+
+    let mut pixels = vec![0; qoi.decoded_size()];
+    let mut px = [0, 0, 0, 255];
+    for out in pixels.chunks_exact_mut(n) {
+        match rng.below(5) {
+            0 => {}
+            1 => px[..n].copy_from_slice(&palette[rng.below(8) as usize * n..][..n]),
+            2 => px.iter_mut().take(3).for_each(|c| {
+                *c = c.wrapping_add(rng.below(4) as u8).wrapping_sub(2);
+            }),
+            3 => px.iter_mut().take(3).for_each(|c| {
+                *c = c.wrapping_add(rng.below(32) as u8).wrapping_sub(16);
+            }),
+            _ => px[..n].iter_mut().for_each(|c| *c = rng.next() as u8),
+        }
+        out.copy_from_slice(&px[..n]);
+    }
+
+    (qoi, pixels)
+}
+
+fn decode_whole(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let qoi = Qoi::decode_header(bytes)?;
+    let mut output = vec![0; qoi.decoded_size()];
+    Qoi::decode(bytes, &mut output)?;
+    Ok(output)
+}
+
+fn decode_windowed(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let qoi = Qoi::decode_header(bytes)?;
+    let mut output = vec![0; qoi.decoded_size()];
+    let data = &bytes[QOI_HEADER_SIZE..];
+    match qoi.colors.has_alpha() {
+        true => windowed::<4>(data, &mut output)?,
+        false => windowed::<3>(data, &mut output)?,
+    }
+    Ok(output)
+}
+
+fn windowed<const N: usize>(mut data: &[u8], mut output: &mut [u8]) -> Result<(), DecodeError>
+where
+    [u8; N]: Pixel,
+{
+    let mut index = [<[u8; N]>::new(); 64];
+    let mut px = <[u8; N]>::new_opaque();
+    let mut run = 0;
+    let mut window = 4;
+
+    while !output.is_empty() {
+        let end = window.min(data.len());
+        let progress = Qoi::decode_range::<N>(&mut index, &mut px, &mut run, &data[..end], output)?;
+
+        data = &data[progress.bytes..];
+        output = &mut output[progress.pixels * N..];
+
+        match progress.bytes == 0 && progress.pixels == 0 {
+            true if end == data.len() || window == 5 => return Err(DecodeError::NotEnoughData),
+            true => window = 5,
+            false => window = 4,
+        }
+    }
+
+    Ok(())
+}
+
+fn check(bytes: &[u8]) {
+    assert_eq!(decode_whole(bytes), decode_windowed(bytes));
+}
+
+#[test]
+fn valid_streams() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..500 {
+        let (qoi, pixels) = image(&mut rng);
+        let bytes = qoi.encode_alloc(&pixels).unwrap();
+
+        assert_eq!(decode_whole(&bytes).unwrap(), pixels);
+        check(&bytes);
+    }
+}
+
+#[test]
+fn corrupted_streams() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for _ in 0..500 {
+        let (qoi, pixels) = image(&mut rng);
+        let mut bytes = qoi.encode_alloc(&pixels).unwrap();
+
+        let data_len = bytes.len() - QOI_HEADER_SIZE;
+        for _ in 0..1 + rng.below(8) {
+            let i = QOI_HEADER_SIZE + rng.below(data_len as u64) as usize;
+            bytes[i] = rng.next() as u8;
+        }
+        check(&bytes);
+    }
+}
+
+#[test]
+fn truncated_streams() {
+    let mut rng = Rng(0xd1b5_4a32_d192_ed03);
+    for _ in 0..500 {
+        let (qoi, pixels) = image(&mut rng);
+        let bytes = qoi.encode_alloc(&pixels).unwrap();
+
+        let data_len = bytes.len() - QOI_HEADER_SIZE;
+        let len = QOI_HEADER_SIZE + rng.below(data_len as u64) as usize;
+        check(&bytes[..len]);
+    }
+}
@@ -0,0 +1,39 @@
+//! Tests for stateful [`Encoder`].
+
+use rapid_qoi::{Colors, EncodeError, Encoder, Qoi};
+
+#[test]
+fn retry_after_output_is_too_small() {
+    let qoi = Qoi {
+        width: 64,
+        height: 4,
+        colors: Colors::Rgba,
+    };
+    let pixels: Vec<u8> = (0..qoi.width * qoi.height)
+        .flat_map(|i| [(i * 7) as u8, (i * 13) as u8, (i / 3) as u8, (i / 16) as u8])
+        .collect();
+
+    let mut bytes = vec![0; qoi.encoded_size_limit()];
+    let mut size = qoi.encode_header(&mut bytes).unwrap();
+
+    let mut encoder = Encoder::new(qoi.colors);
+    let half = pixels.len() / 2;
+    size += encoder
+        .push_pixels(&pixels[..half], &mut bytes[size..])
+        .unwrap();
+
+    // Fails after encoding some of the pixels.
+    assert_eq!(
+        encoder.push_pixels(&pixels[half..], &mut bytes[size..size + 64]),
+        Err(EncodeError::OutputIsTooSmall)
+    );
+
+    size += encoder
+        .push_pixels(&pixels[half..], &mut bytes[size..])
+        .unwrap();
+    size += encoder.finish(&mut bytes[size..]).unwrap();
+
+    let mut expected = vec![0; qoi.encoded_size_limit()];
+    let expected_size = qoi.encode(&pixels, &mut expected).unwrap();
+    assert_eq!(bytes[..size], expected[..expected_size]);
+}
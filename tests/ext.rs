@@ -0,0 +1,61 @@
+//! Tests for images with extended header.
+
+#![cfg(feature = "ext")]
+
+use rapid_qoi::{meta::FrameTable, EncodeError, Rect};
+
+#[test]
+fn frame_table_rejects_long_name() {
+    let rect = Rect {
+        x: 0,
+        y: 0,
+        width: 1,
+        height: 1,
+    };
+    let long = "f".repeat(256);
+
+    let mut output = [0; 1024];
+    assert_eq!(
+        FrameTable::write(&[("short", rect), (&long, rect)], &mut output),
+        Err(EncodeError::InvalidMetadata)
+    );
+    assert!(output.iter().all(|&b| b == 0));
+}
+
+#[cfg(feature = "experimental")]
+#[test]
+fn decode_frame_rejects_paeth_filter() {
+    use rapid_qoi::{
+        ext::{Capabilities, ExtHeader},
+        meta::decode_frame,
+        Colors, DecodeError, Qoi,
+    };
+
+    let qoi = Qoi {
+        width: 4,
+        height: 4,
+        colors: Colors::Rgba,
+    };
+    let pixels: Vec<u8> = (0..64).map(|i| (i * 17) as u8).collect();
+
+    let rect = Rect {
+        x: 1,
+        y: 1,
+        width: 2,
+        height: 2,
+    };
+    let mut metadata = vec![0; 64];
+    let size = FrameTable::write(&[("frame", rect)], &mut metadata).unwrap();
+    metadata.truncate(size);
+
+    let header = ExtHeader::new(qoi, Capabilities::METADATA | Capabilities::PAETH_FILTER);
+    let mut bytes = vec![0; 1024];
+    let size = header.encode_image(&metadata, &pixels, &mut bytes).unwrap();
+    bytes.truncate(size);
+
+    let mut output = vec![0; 16];
+    assert_eq!(
+        decode_frame(&bytes, "frame", &mut output),
+        Err(DecodeError::UnsupportedCapabilities)
+    );
+}
@@ -0,0 +1,118 @@
+//! Verifies that decoding and encoding never panic on arbitrary input.\
+//! Each function below fails to link if the compiler cannot prove that it never panics.
+//! Proof requires optimizations and LTO, so this target only checks optimized builds:
+//! `cargo test --profile no-panic --test no_panic`.
+
+#![cfg(not(debug_assertions))]
+
+use no_panic::no_panic;
+use rapid_qoi::{DecodeError, EncodeError, Qoi, RangeProgress};
+
+#[no_panic]
+fn decode_header(bytes: &[u8]) -> Result<Qoi, DecodeError> {
+    Qoi::decode_header(bytes)
+}
+
+#[no_panic]
+fn decode(bytes: &[u8], output: &mut [u8]) -> Result<Qoi, DecodeError> {
+    Qoi::decode(bytes, output)
+}
+
+#[no_panic]
+fn decode_range_rgb(
+    index: &mut [[u8; 3]; 64],
+    px: &mut [u8; 3],
+    run: &mut usize,
+    bytes: &[u8],
+    pixels: &mut [u8],
+) -> Result<RangeProgress, DecodeError> {
+    Qoi::decode_range::<3>(index, px, run, bytes, pixels)
+}
+
+#[no_panic]
+fn decode_range_rgba(
+    index: &mut [[u8; 4]; 64],
+    px: &mut [u8; 4],
+    run: &mut usize,
+    bytes: &[u8],
+    pixels: &mut [u8],
+) -> Result<RangeProgress, DecodeError> {
+    Qoi::decode_range::<4>(index, px, run, bytes, pixels)
+}
+
+#[no_panic]
+fn encode(qoi: &Qoi, pixels: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+    qoi.encode(pixels, output)
+}
+
+#[no_panic]
+fn encode_range_rgb(
+    index: &mut [[u8; 4]; 64],
+    px: &mut [u8; 3],
+    run: &mut usize,
+    pixels: &[u8],
+    output: &mut [u8],
+) -> Result<usize, EncodeError> {
+    Qoi::encode_range::<3>(index, px, run, pixels, output)
+}
+
+#[no_panic]
+fn encode_range_rgba(
+    index: &mut [[u8; 4]; 64],
+    px: &mut [u8; 4],
+    run: &mut usize,
+    pixels: &[u8],
+    output: &mut [u8],
+) -> Result<usize, EncodeError> {
+    Qoi::encode_range::<4>(index, px, run, pixels, output)
+}
+
+#[test]
+fn never_panics() {
+    // Bytes are not a valid image, every call must return an error or partial progress.
+    let bytes = [0xfeu8; 64];
+    let mut output = [0u8; 64];
+
+    assert!(decode_header(&bytes).is_err());
+    assert!(decode(&bytes, &mut output).is_err());
+
+    let _ = decode_range_rgb(
+        &mut [[0; 3]; 64],
+        &mut [0; 3],
+        &mut 0,
+        &bytes,
+        &mut output[..62],
+    );
+    let _ = decode_range_rgba(
+        &mut [[0; 4]; 64],
+        &mut [0; 4],
+        &mut 0,
+        &bytes,
+        &mut output[..63],
+    );
+
+    let qoi = Qoi {
+        width: u32::MAX,
+        height: u32::MAX,
+        colors: rapid_qoi::Colors::Rgba,
+    };
+    assert!(encode(&qoi, &bytes, &mut output).is_err());
+
+    assert_eq!(
+        encode_range_rgb(
+            &mut [[0; 4]; 64],
+            &mut [0; 3],
+            &mut 70,
+            &bytes[..62],
+            &mut output
+        ),
+        Err(EncodeError::InvalidOp)
+    );
+    let _ = encode_range_rgba(
+        &mut [[0; 4]; 64],
+        &mut [0; 4],
+        &mut 0,
+        &bytes[..63],
+        &mut output,
+    );
+}
@@ -0,0 +1,244 @@
+//! [`EncodeOptions::REFERENCE`] must reproduce output of the reference encoder
+//! and every combination of encoder heuristics must produce image that decodes into original pixels.\
+//! Strict decoding must reject images with missing or corrupt end marker and trailing bytes,
+//! which lenient decoding ignores.
+
+use rapid_qoi::{ChannelOrder, Colors, DecodeError, DecodeOptions, EncodeOptions, Qoi};
+
+/// Returns pixels mixing runs longer than 62 pixels, single repeated pixels,
+/// small differences, repeated colors and literals, so every op and heuristic is exercised.
+fn pixels(qoi: &Qoi) -> Vec<u8> {
+    let channels = qoi.colors.channels();
+    let mut pixels = Vec::new();
+    let mut px = [0u8, 0, 0, 255];
+    for i in 0..qoi.width * qoi.height {
+        match i % 113 {
+            // Long run.
+            0..=69 => {}
+            // Single repeated pixels.
+            70..=79 if i % 2 == 0 => {}
+            70..=79 => px[2] = px[2].wrapping_sub(1),
+            // Luma.
+            80..=84 => {
+                px[1] = px[1].wrapping_add(20);
+                px[0] = px[0].wrapping_add(23);
+            }
+            // Repeated colors from index.
+            85..=89 => px = [1, 2, 3, 255],
+            90..=94 => px = [4, 5, 6, 255],
+            // Literals, with alpha changes for RGBA.
+            _ => {
+                px = [
+                    (i * 37) as u8,
+                    (i * 91) as u8,
+                    (i * 13) as u8,
+                    (i / 3) as u8,
+                ]
+            }
+        }
+        pixels.extend_from_slice(&px[..channels]);
+    }
+    pixels
+}
+
+/// Straightforward port of `qoi_encode` from the reference implementation.
+fn encode_reference(qoi: &Qoi, pixels: &[u8]) -> Vec<u8> {
+    let channels = qoi.colors.channels();
+
+    let mut bytes = vec![0; 14];
+    qoi.encode_header(&mut bytes).unwrap();
+
+    let mut index = [[0u8; 4]; 64];
+    let mut prev = [0u8, 0, 0, 255];
+    let mut run = 0;
+
+    let count = pixels.len() / channels;
+    for (i, chunk) in pixels.chunks_exact(channels).enumerate() {
+        let mut px = [0, 0, 0, 255];
+        px[..channels].copy_from_slice(chunk);
+
+        if px == prev {
+            run += 1;
+            if run == 62 || i == count - 1 {
+                bytes.push(0xc0 | (run - 1));
+                run = 0;
+            }
+            continue;
+        }
+
+        if run > 0 {
+            bytes.push(0xc0 | (run - 1));
+            run = 0;
+        }
+
+        let [r, g, b, a] = px;
+        let hash = (r as usize * 3 + g as usize * 5 + b as usize * 7 + a as usize * 11) % 64;
+
+        if index[hash] == px {
+            bytes.push(hash as u8);
+        } else {
+            index[hash] = px;
+
+            if a == prev[3] {
+                let vr = r.wrapping_sub(prev[0]) as i8 as i32;
+                let vg = g.wrapping_sub(prev[1]) as i8 as i32;
+                let vb = b.wrapping_sub(prev[2]) as i8 as i32;
+                let vg_r = vr - vg;
+                let vg_b = vb - vg;
+
+                if (-2..2).contains(&vr) && (-2..2).contains(&vg) && (-2..2).contains(&vb) {
+                    bytes.push(0x40 | ((vr + 2) << 4 | (vg + 2) << 2 | (vb + 2)) as u8);
+                } else if (-8..8).contains(&vg_r)
+                    && (-32..32).contains(&vg)
+                    && (-8..8).contains(&vg_b)
+                {
+                    bytes.push(0x80 | (vg + 32) as u8);
+                    bytes.push(((vg_r + 8) << 4 | (vg_b + 8)) as u8);
+                } else {
+                    bytes.extend_from_slice(&[0xfe, r, g, b]);
+                }
+            } else {
+                bytes.extend_from_slice(&[0xff, r, g, b, a]);
+            }
+        }
+
+        prev = px;
+    }
+
+    bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    bytes
+}
+
+fn encode(qoi: &Qoi, pixels: &[u8], options: &EncodeOptions) -> Vec<u8> {
+    let mut bytes = vec![0; qoi.encoded_size_limit()];
+    let size = qoi
+        .encode_with_options(pixels, &mut bytes, options)
+        .unwrap();
+    bytes.truncate(size);
+    bytes
+}
+
+fn images() -> impl Iterator<Item = Qoi> {
+    [Colors::Srgb, Colors::SrgbLinA, Colors::Rgb, Colors::Rgba]
+        .iter()
+        .copied()
+        .flat_map(|colors| {
+            [(1, 1), (62, 1), (63, 1), (127, 9)]
+                .iter()
+                .copied()
+                .map(move |(width, height)| Qoi {
+                    width,
+                    height,
+                    colors,
+                })
+        })
+}
+
+#[test]
+fn reference_options_match_reference_encoder() {
+    for qoi in images() {
+        let pixels = pixels(&qoi);
+        assert_eq!(
+            encode(&qoi, &pixels, &EncodeOptions::REFERENCE),
+            encode_reference(&qoi, &pixels),
+            "{}x{} {:?}",
+            qoi.width,
+            qoi.height,
+            qoi.colors
+        );
+    }
+}
+
+#[test]
+fn options_round_trip() {
+    for qoi in images() {
+        let pixels = pixels(&qoi);
+        let mut decoded = vec![0; qoi.decoded_size()];
+
+        for index_single_run in [false, true] {
+            for index_lookup in [false, true] {
+                // Zero and values above 62 are clamped.
+                for max_run in [0, 1, 2, 17, 61, 62, 255] {
+                    let options = EncodeOptions {
+                        index_single_run,
+                        index_lookup,
+                        max_run,
+                    };
+
+                    let bytes = encode(&qoi, &pixels, &options);
+                    Qoi::decode(&bytes, &mut decoded).unwrap();
+                    assert_eq!(decoded, pixels, "{:?}", options);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn strict_end_marker_and_trailing_data() {
+    let qoi = Qoi {
+        width: 127,
+        height: 9,
+        colors: Colors::Rgba,
+    };
+    let pixels = pixels(&qoi);
+    let bytes = encode(&qoi, &pixels, &EncodeOptions::DEFAULT);
+    let ops_end = bytes.len() - 8;
+
+    let mut corrupt = bytes.clone();
+    *corrupt.last_mut().unwrap() = 0;
+
+    let mut trailing = bytes.clone();
+    trailing.extend_from_slice(&[0, 0, 0, 1]);
+
+    // Input, error in strict mode and bytes consumed in lenient mode.
+    let cases = [
+        (&bytes[..], None, bytes.len()),
+        (
+            &bytes[..ops_end],
+            Some(DecodeError::InvalidEndMarker),
+            ops_end,
+        ),
+        (
+            &bytes[..bytes.len() - 1],
+            Some(DecodeError::InvalidEndMarker),
+            ops_end,
+        ),
+        (&corrupt[..], Some(DecodeError::InvalidEndMarker), ops_end),
+        (&trailing[..], Some(DecodeError::TrailingData), bytes.len()),
+    ];
+
+    // Channel order other than RGBA takes different decoding path.
+    for &order in &[ChannelOrder::Rgba, ChannelOrder::Bgra] {
+        let expected = match order {
+            ChannelOrder::Bgra => pixels
+                .chunks_exact(4)
+                .flat_map(|px| [px[2], px[1], px[0], px[3]])
+                .collect(),
+            _ => pixels.clone(),
+        };
+
+        for &(input, strict_err, lenient_consumed) in &cases {
+            let mut output = vec![0; pixels.len()];
+
+            let options = DecodeOptions {
+                order,
+                ..DecodeOptions::default()
+            };
+            let (_, consumed) =
+                Qoi::decode_with_options_consumed(input, &mut output, &options).unwrap();
+            assert_eq!(consumed, lenient_consumed);
+            assert_eq!(output, expected);
+
+            let options = DecodeOptions {
+                strict: true,
+                ..options
+            };
+            let result = Qoi::decode_with_options_consumed(input, &mut output, &options);
+            match strict_err {
+                None => assert_eq!(result.unwrap().1, input.len()),
+                Some(err) => assert_eq!(result.unwrap_err(), err),
+            }
+        }
+    }
+}
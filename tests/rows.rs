@@ -0,0 +1,47 @@
+//! Row layout arithmetic must fail with error instead of overflowing.
+
+use rapid_qoi::{Colors, DecodeError, EncodeError, Qoi};
+
+#[test]
+fn aligned_size_overflow() {
+    let qoi = Qoi {
+        width: 3,
+        height: 2,
+        colors: Colors::Rgba,
+    };
+
+    assert_eq!(qoi.aligned_row_pitch(256), Ok(256));
+    assert_eq!(qoi.aligned_size(256), Ok(512));
+
+    let alignment = usize::MAX / 2 + 1;
+    assert_eq!(qoi.aligned_row_pitch(alignment), Ok(alignment));
+    assert_eq!(qoi.aligned_size(alignment), Err(DecodeError::TooLarge));
+    assert_eq!(qoi.aligned_row_pitch(usize::MAX), Ok(usize::MAX));
+}
+
+#[test]
+fn encode_strided_at_overflow() {
+    let qoi = Qoi {
+        width: 2,
+        height: 2,
+        colors: Colors::Rgba,
+    };
+    let pixels = [0; 64];
+    let mut output = [0; 64];
+
+    assert!(qoi
+        .encode_strided_at(&pixels, 16, 1, 1, &mut output)
+        .is_ok());
+    assert_eq!(
+        qoi.encode_strided_at(&pixels, usize::MAX / 2, 0, 2, &mut output),
+        Err(EncodeError::NotEnoughPixelData)
+    );
+    assert_eq!(
+        qoi.encode_strided_at(&pixels, usize::MAX / 2, 0, 1, &mut output),
+        Err(EncodeError::NotEnoughPixelData)
+    );
+    assert_eq!(
+        qoi.encode_strided_at(&pixels, usize::MAX, u32::MAX, 0, &mut output),
+        Err(EncodeError::NotEnoughPixelData)
+    );
+}
@@ -0,0 +1,36 @@
+//! Tests for saving and restoring codec state.
+
+use rapid_qoi::{DecState, EncState};
+
+/// Offset of the pending run in serialized state.
+const RUN_OFFSET: usize = 4 + 64 * 4 + 4;
+
+fn with_run(bytes: [u8; EncState::SIZE], run: u32) -> [u8; EncState::SIZE] {
+    let mut bytes = bytes;
+    bytes[RUN_OFFSET..RUN_OFFSET + 4].copy_from_slice(&run.to_be_bytes());
+    bytes
+}
+
+#[test]
+fn enc_state_run_round_trip() {
+    let bytes = with_run(EncState::new().to_bytes(), 61);
+    let state = EncState::from_bytes(&bytes).unwrap();
+    assert_eq!(state.to_bytes(), bytes);
+
+    assert_eq!(
+        EncState::from_bytes(&with_run(EncState::new().to_bytes(), 62)),
+        None
+    );
+}
+
+#[test]
+fn dec_state_run_round_trip() {
+    let bytes = with_run(DecState::new().to_bytes(), 61);
+    let state = DecState::from_bytes(&bytes).unwrap();
+    assert_eq!(state.to_bytes(), bytes);
+
+    assert_eq!(
+        DecState::from_bytes(&with_run(DecState::new().to_bytes(), 62)),
+        None
+    );
+}
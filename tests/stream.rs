@@ -0,0 +1,133 @@
+//! Encoders writing output chunk by chunk must produce the same image as [`Qoi::encode_alloc`],
+//! decoders reading input chunk by chunk must restore its pixels.
+
+#![cfg(feature = "std")]
+
+use rapid_qoi::{Colors, Qoi, QoiWriteEncoder};
+
+/// Returns RGBA image whose first chunk of 4096 pixels ends with pending run,
+/// while every pixel of the second chunk takes 5 bytes.\
+/// Second chunk is the largest possible output for a chunk:
+/// flushed run, `QOI_OP_RGBA` for each pixel and the end marker.
+fn worst_case_chunks() -> (Qoi, Vec<u8>) {
+    const WIDTH: usize = 8192;
+
+    let mut pixels = Vec::with_capacity(WIDTH * 4);
+    for i in 0..WIDTH {
+        let v = if i == 4095 { 4094 } else { i };
+        let a = if i >= 4096 && i % 2 == 0 { 128 } else { 255 };
+        pixels.extend_from_slice(&[(v * 37) as u8, (v * 91 / 8) as u8, (v * 13 / 32) as u8, a]);
+    }
+
+    let qoi = Qoi {
+        width: WIDTH as u32,
+        height: 1,
+        colors: Colors::Rgba,
+    };
+    (qoi, pixels)
+}
+
+#[test]
+fn write_encoder_worst_case_chunk() {
+    let (qoi, pixels) = worst_case_chunks();
+
+    let mut encoder = QoiWriteEncoder::new(qoi, Vec::new()).unwrap();
+    encoder.write_pixels(&pixels).unwrap();
+    let bytes = encoder.finish().unwrap();
+
+    assert_eq!(bytes, qoi.encode_alloc(&pixels).unwrap());
+}
+
+/// Polls future to completion.\
+/// Writers used in tests never return `Poll::Pending`, so no real executor is needed.
+#[cfg(any(feature = "futures", feature = "tokio"))]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll, Waker};
+
+    let mut future = std::pin::pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn encode_async_worst_case_chunk() {
+    let (qoi, pixels) = worst_case_chunks();
+
+    let mut bytes = Vec::new();
+    block_on(qoi.encode_async(&pixels, &mut bytes)).unwrap();
+
+    assert_eq!(bytes, qoi.encode_alloc(&pixels).unwrap());
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn encode_tokio_worst_case_chunk() {
+    let (qoi, pixels) = worst_case_chunks();
+
+    let mut bytes = Vec::new();
+    block_on(qoi.encode_tokio(&pixels, &mut bytes)).unwrap();
+
+    assert_eq!(bytes, qoi.encode_alloc(&pixels).unwrap());
+}
+
+/// Header of 65536x65536 RGBA image, 16 GiB decoded, followed by a few run ops.
+#[cfg(any(feature = "futures", feature = "tokio"))]
+fn huge_truncated() -> Vec<u8> {
+    let mut bytes = b"qoif".to_vec();
+    bytes.extend_from_slice(&65536u32.to_be_bytes());
+    bytes.extend_from_slice(&65536u32.to_be_bytes());
+    bytes.extend_from_slice(&[4, 0]);
+    bytes.extend_from_slice(&[0xfd; 6]);
+    bytes
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn decode_async_chunks() {
+    let (qoi, pixels) = worst_case_chunks();
+    let bytes = qoi.encode_alloc(&pixels).unwrap();
+
+    let (_, decoded) = block_on(Qoi::decode_async(&mut &bytes[..])).unwrap();
+    assert_eq!(decoded, pixels);
+
+    let err = block_on(Qoi::decode_async(&mut &huge_truncated()[..])).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn decode_tokio_chunks() {
+    let (qoi, pixels) = worst_case_chunks();
+    let bytes = qoi.encode_alloc(&pixels).unwrap();
+
+    let (_, decoded) = block_on(Qoi::decode_tokio(&mut &bytes[..])).unwrap();
+    assert_eq!(decoded, pixels);
+
+    let err = block_on(Qoi::decode_tokio(&mut &huge_truncated()[..])).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn codec_rejects_long_frame_without_allocating() {
+    use tokio_util::codec::Encoder;
+
+    let (qoi, pixels) = worst_case_chunks();
+
+    let mut codec = rapid_qoi::QoiCodec::with_max_length(1024);
+    let mut dst = bytes::BytesMut::new();
+    let err = codec.encode((qoi, &pixels), &mut dst).unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(dst.is_empty());
+    assert!(dst.capacity() < qoi.encoded_size_limit());
+
+    let mut codec = rapid_qoi::QoiCodec::new();
+    codec.encode((qoi, &pixels), &mut dst).unwrap();
+    assert_eq!(&dst[4..], qoi.encode_alloc(&pixels).unwrap());
+}
@@ -0,0 +1,152 @@
+//! Tests for user-defined pixel types.
+
+use rapid_qoi::{Pixel, Qoi};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Bgra {
+    b: u8,
+    g: u8,
+    r: u8,
+    a: u8,
+}
+
+rapid_qoi::impl_pixel!(Bgra { r, g, b, a });
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Bgr {
+    b: u8,
+    g: u8,
+    r: u8,
+}
+
+rapid_qoi::impl_pixel!(Bgr { r, g, b });
+
+/// Returns RGBA bytes with runs crossing chunk boundaries.
+fn rgba_bytes() -> Vec<u8> {
+    (0..1000u32)
+        .flat_map(|i| {
+            let v = i / 100;
+            [
+                (v * 37) as u8,
+                (v * 11 + i % 3) as u8,
+                (i / 7) as u8,
+                255 - (v as u8 & 1),
+            ]
+        })
+        .collect()
+}
+
+#[test]
+fn range_pixels_match_bytes_rgba() {
+    let bytes = rgba_bytes();
+    let pixels: Vec<Bgra> = bytes
+        .chunks_exact(4)
+        .map(|px| Bgra {
+            r: px[0],
+            g: px[1],
+            b: px[2],
+            a: px[3],
+        })
+        .collect();
+
+    let mut expected = vec![0; 8192];
+    let size = Qoi::encode_range::<4>(
+        &mut [[0; 4]; 64],
+        &mut [0, 0, 0, 255],
+        &mut 0,
+        &bytes,
+        &mut expected,
+    )
+    .unwrap();
+
+    let mut output = vec![0; 8192];
+    let mut px_prev = Bgra::new_opaque();
+    let written = Qoi::encode_range_pixels(
+        &mut [[0; 4]; 64],
+        &mut px_prev,
+        &mut 0,
+        &pixels,
+        &mut output,
+    )
+    .unwrap();
+    assert_eq!(output[..written], expected[..size]);
+    assert_eq!(px_prev, pixels[pixels.len() - 1]);
+
+    let mut decoded = vec![Bgra::new(); pixels.len()];
+    let progress = Qoi::decode_range_pixels(
+        &mut [Bgra::new(); 64],
+        &mut Bgra::new_opaque(),
+        &mut 0,
+        &output[..written],
+        &mut decoded,
+    )
+    .unwrap();
+    assert_eq!(progress.bytes, written);
+    assert_eq!(progress.pixels, pixels.len());
+    assert_eq!(decoded, pixels);
+}
+
+#[test]
+fn range_pixels_match_bytes_rgb() {
+    let bytes: Vec<u8> = rgba_bytes()
+        .chunks_exact(4)
+        .flat_map(|px| [px[0], px[1], px[2]])
+        .collect();
+    let pixels: Vec<Bgr> = bytes
+        .chunks_exact(3)
+        .map(|px| Bgr {
+            r: px[0],
+            g: px[1],
+            b: px[2],
+        })
+        .collect();
+
+    let mut expected = vec![0; 8192];
+    let size = Qoi::encode_range::<3>(
+        &mut [[0; 4]; 64],
+        &mut [0, 0, 0],
+        &mut 0,
+        &bytes,
+        &mut expected,
+    )
+    .unwrap();
+
+    let mut output = vec![0; 8192];
+    let written = Qoi::encode_range_pixels(
+        &mut [[0; 4]; 64],
+        &mut Bgr::new_opaque(),
+        &mut 0,
+        &pixels,
+        &mut output,
+    )
+    .unwrap();
+    assert_eq!(output[..written], expected[..size]);
+
+    // Decoding stops in the middle of the stream when pixels slice is filled.
+    let mut decoded = vec![Bgr::new(); 300];
+    let mut index = [Bgr::new(); 64];
+    let mut px = Bgr::new_opaque();
+    let mut run = 0;
+    let first = Qoi::decode_range_pixels(
+        &mut index,
+        &mut px,
+        &mut run,
+        &output[..written],
+        &mut decoded,
+    )
+    .unwrap();
+    assert_eq!(first.pixels, 300);
+    assert_eq!(decoded, pixels[..300]);
+
+    let mut rest = vec![Bgr::new(); pixels.len() - 300];
+    let second = Qoi::decode_range_pixels(
+        &mut index,
+        &mut px,
+        &mut run,
+        &output[first.bytes..written],
+        &mut rest,
+    )
+    .unwrap();
+    assert_eq!(second.pixels, rest.len());
+    assert_eq!(rest, pixels[300..]);
+}
@@ -0,0 +1,76 @@
+//! WebAssembly bindings of rapid-qoi.
+//!
+//! Build with `wasm-pack build wasm` to get JavaScript package
+//! exporting `decode` and `encode` functions.
+
+use rapid_qoi::{Channels, Colors, DecodeError, Qoi};
+use wasm_bindgen::{prelude::*, Clamped};
+
+/// Decoded image.\
+/// Pixels are always RGBA, suitable for `ImageData`.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct DecodedImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl DecodedImage {
+    /// Width of the image in pixels.
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the image in pixels.
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// RGBA pixels of the image as `Uint8ClampedArray`.\
+    /// Can be passed directly to `ImageData` constructor.
+    #[wasm_bindgen(getter)]
+    pub fn pixels(&self) -> Clamped<Vec<u8>> {
+        Clamped(self.pixels.clone())
+    }
+}
+
+/// Decodes QOI image into RGBA pixels.\
+/// Alpha of images without alpha channel is set to `255`.
+#[wasm_bindgen]
+pub fn decode(bytes: &[u8]) -> Result<DecodedImage, JsError> {
+    let qoi = Qoi::decode_header(bytes)?;
+
+    // Size of RGBA output, regardless of channels of the image.
+    let size = Qoi {
+        colors: Colors::Rgba,
+        ..qoi
+    }
+    .checked_decoded_size()
+    .ok_or(DecodeError::TooLarge)?;
+
+    let mut pixels = vec![0; size];
+    Qoi::decode_with_channels(bytes, &mut pixels, Channels::Rgba)?;
+
+    Ok(DecodedImage {
+        width: qoi.width,
+        height: qoi.height,
+        pixels,
+    })
+}
+
+/// Encodes RGBA pixels, such as `ImageData.data`, into QOI image.\
+/// Image is encoded as sRGB with linear alpha.
+#[wasm_bindgen]
+pub fn encode(pixels: &[u8], width: u32, height: u32) -> Result<Vec<u8>, JsError> {
+    let qoi = Qoi {
+        width,
+        height,
+        colors: Colors::SrgbLinA,
+    };
+
+    Ok(qoi.encode_alloc(pixels)?)
+}